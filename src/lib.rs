@@ -1,6 +1,9 @@
 use crate::routes::{
-    collection_routes, database_routes, health_routes, object_routes, search_routes,
+    acl_routes, collection_routes, database_routes, health_routes, index_routes, key_routes,
+    object_routes, relation_routes, search_routes, share_routes, trash_routes,
 };
+use crate::services::{auth_service, rate_limit_service, sweep_service};
+use crate::utils::api_response::{error_response, handle};
 use serde_json::json;
 use worker::{event, Env, Request, Response, Result, Router};
 mod models;
@@ -11,9 +14,14 @@ use worker::*;
 
 #[event(fetch)]
 pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Response> {
-    let router = Router::new();
+    let auth = match auth_service::authorize_request(&req, &env).await {
+        Ok(auth) => auth,
+        Err(err) => return error_response(&err),
+    };
+    let token = auth.principal.as_ref().map(|p| p.token.clone());
+    let router = Router::with_data(auth);
 
-    router
+    let mut response = router
         .get_async("/", |_, _| async move {
             Response::from_json(&json!({
                 "name": "GistDB",
@@ -29,7 +37,19 @@ pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Respo
                     "Multiple collections per database",
                     "JSON document storage",
                     "Full CRUD operations",
-                    "GitHub token authentication"
+                    "GitHub token authentication",
+                    "Scoped, revocable API keys",
+                    "JSON Schema validation per collection",
+                    "Unique constraints on single or compound fields",
+                    "Default values and computed fields",
+                    "Referential integrity with restrict, cascade and set_null",
+                    "Soft delete with restore, purge and retention",
+                    "Object expiry with a scheduled sweeper",
+                    "Revision history and point-in-time reads",
+                    "Object change history with diffs",
+                    "Rollback to a previous revision with dry-run diff",
+                    "Database export and import as portable JSON bundles",
+                    "Collection export and import as NDJSON or CSV"
                 ],
                 "endpoints": {
                     "root": {
@@ -44,21 +64,60 @@ pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Respo
                     },
                     "databases": {
                         "POST /api/databases": "Create a new database",
+                        "GET /api/databases": "List databases owned by the token",
                         "GET /api/:gistId": "Get entire database contents",
-                        "DELETE /api/databases": "Delete a database"
+                        "DELETE /api/databases": "Delete a database",
+                        "POST /api/databases/:gistId/migrate": "Upgrade a database to the current storage format",
+                        "POST /api/databases/:gistId/sweep": "Remove expired objects and trash now",
+                        "GET /api/databases/:gistId/revisions": "List the database's revisions",
+                        "GET /api/databases/:gistId/history": "Show how one object changed across revisions",
+                        "POST /api/databases/:gistId/rollback": "Restore the database or some collections to a revision",
+                        "GET /api/databases/:gistId/export": "Download the database as a JSON bundle",
+                        "POST /api/databases/import": "Create a database from a JSON bundle",
+                        "GET /api/databases/:gistId/acl": "Get collection access rules",
+                        "PUT /api/databases/:gistId/acl": "Replace collection access rules"
                     },
                     "collections": {
                         "POST /api/collections": "Create a new collection",
                         "POST /api/collections/get": "Get collection contents",
-                        "DELETE /api/collections": "Delete a collection"
+                        "DELETE /api/collections": "Delete a collection",
+                        "PUT /api/collections/schema": "Set or remove a collection's JSON Schema",
+                        "PUT /api/collections/fields": "Set a collection's default and computed fields",
+                        "PUT /api/collections/trash": "Enable soft delete and set trash retention",
+                        "PUT /api/collections/ttl": "Enable object expiry and set a default TTL",
+                        "GET /api/databases/:gistId/collections/:collectionName/export": "Download a collection as NDJSON or CSV",
+                        "POST /api/databases/:gistId/collections/:collectionName/import": "Add objects from NDJSON or CSV"
+                    },
+                    "indexes": {
+                        "POST /api/indexes": "Declare an index or unique constraint",
+                        "DELETE /api/indexes": "Drop an index"
+                    },
+                    "trash": {
+                        "GET /api/databases/:gistId/trash": "List soft-deleted objects and collections",
+                        "POST /api/trash/restore": "Restore an object or collection from the trash",
+                        "DELETE /api/trash": "Permanently purge trashed items"
+                    },
+                    "relations": {
+                        "POST /api/relations": "Declare a reference between collections",
+                        "DELETE /api/relations": "Drop a relation"
                     },
                     "objects": {
                         "POST /api/objects": "Create a new object",
                         "PUT /api/objects": "Update an existing object",
-                        "DELETE /api/objects": "Delete an object"
+                        "DELETE /api/objects": "Delete an object",
+                        "POST /api/objects/update_many": "Apply update operators to every object matching a filter",
+                        "POST /api/objects/delete_many": "Delete every object matching a filter"
                     },
                     "search":{
                         "POST /api/search": "Search objects"
+                    },
+                    "share": {
+                        "POST /api/share": "Create an expiring read-only share token"
+                    },
+                    "keys": {
+                        "POST /api/keys": "Create a scoped API key",
+                        "GET /api/keys": "List API keys",
+                        "DELETE /api/keys/:keyId": "Revoke an API key"
                     }
                 },
                 "documentation": "https://github.com/MdSadiqMd/GistDB"
@@ -77,15 +136,134 @@ pub async fn main(req: Request, env: Env, _ctx: worker::Context) -> Result<Respo
             Response::from_bytes(include_bytes!("../docs/swagger-ui/index.html").to_vec())
                 .map(|resp| resp.with_headers(headers))
         })
-        .post_async("/api/databases", database_routes::create_database)
-        .delete_async("/api/databases", database_routes::delete_database)
-        .post_async("/api/collections", collection_routes::create_collection)
-        .get_async("/api/:gistId", collection_routes::get_collection)
-        .delete_async("/api/collections", collection_routes::delete_collection)
-        .post_async("/api/objects", object_routes::create_object)
-        .put_async("/api/objects", object_routes::update_object)
-        .delete_async("/api/objects", object_routes::delete_object)
-        .post_async("/api/search", search_routes::search_objects)
+        .post_async("/api/databases", |req, ctx| {
+            handle(database_routes::create_database(req, ctx))
+        })
+        .get_async("/api/databases", |req, ctx| {
+            handle(database_routes::list_databases(req, ctx))
+        })
+        .delete_async("/api/databases", |req, ctx| {
+            handle(database_routes::delete_database(req, ctx))
+        })
+        .post_async("/api/databases/:gistId/migrate", |req, ctx| {
+            handle(database_routes::migrate_database(req, ctx))
+        })
+        .post_async("/api/databases/:gistId/sweep", |req, ctx| {
+            handle(database_routes::sweep_database(req, ctx))
+        })
+        .post_async("/api/databases/import", |req, ctx| {
+            handle(database_routes::import_database(req, ctx))
+        })
+        .get_async("/api/databases/:gistId/export", |req, ctx| {
+            handle(database_routes::export_database(req, ctx))
+        })
+        .get_async(
+            "/api/databases/:gistId/collections/:collectionName/export",
+            |req, ctx| handle(collection_routes::export_collection(req, ctx)),
+        )
+        .post_async(
+            "/api/databases/:gistId/collections/:collectionName/import",
+            |req, ctx| handle(collection_routes::import_collection(req, ctx)),
+        )
+        .post_async("/api/databases/:gistId/rollback", |req, ctx| {
+            handle(database_routes::rollback_database(req, ctx))
+        })
+        .get_async("/api/databases/:gistId/revisions", |req, ctx| {
+            handle(database_routes::list_revisions(req, ctx))
+        })
+        .get_async("/api/databases/:gistId/history", |req, ctx| {
+            handle(database_routes::object_history(req, ctx))
+        })
+        .get_async("/api/databases/:gistId/trash", |req, ctx| {
+            handle(trash_routes::list_trash(req, ctx))
+        })
+        .post_async("/api/trash/restore", |req, ctx| {
+            handle(trash_routes::restore(req, ctx))
+        })
+        .delete_async("/api/trash", |req, ctx| {
+            handle(trash_routes::purge(req, ctx))
+        })
+        .get_async("/api/databases/:gistId/acl", |req, ctx| {
+            handle(acl_routes::get_acl(req, ctx))
+        })
+        .put_async("/api/databases/:gistId/acl", |req, ctx| {
+            handle(acl_routes::put_acl(req, ctx))
+        })
+        .post_async("/api/keys", |req, ctx| {
+            handle(key_routes::create_key(req, ctx))
+        })
+        .get_async("/api/keys", |req, ctx| {
+            handle(key_routes::list_keys(req, ctx))
+        })
+        .delete_async("/api/keys/:keyId", |req, ctx| {
+            handle(key_routes::revoke_key(req, ctx))
+        })
+        .post_async("/api/collections", |req, ctx| {
+            handle(collection_routes::create_collection(req, ctx))
+        })
+        .put_async("/api/collections/schema", |req, ctx| {
+            handle(collection_routes::set_schema(req, ctx))
+        })
+        .put_async("/api/collections/fields", |req, ctx| {
+            handle(collection_routes::set_fields(req, ctx))
+        })
+        .put_async("/api/collections/ttl", |req, ctx| {
+            handle(collection_routes::set_ttl(req, ctx))
+        })
+        .put_async("/api/collections/trash", |req, ctx| {
+            handle(collection_routes::set_soft_delete(req, ctx))
+        })
+        .post_async("/api/indexes", |req, ctx| {
+            handle(index_routes::create_index(req, ctx))
+        })
+        .delete_async("/api/indexes", |req, ctx| {
+            handle(index_routes::delete_index(req, ctx))
+        })
+        .post_async("/api/relations", |req, ctx| {
+            handle(relation_routes::create_relation(req, ctx))
+        })
+        .delete_async("/api/relations", |req, ctx| {
+            handle(relation_routes::delete_relation(req, ctx))
+        })
+        .get_async("/api/:gistId", |req, ctx| {
+            handle(collection_routes::get_collection(req, ctx))
+        })
+        .delete_async("/api/collections", |req, ctx| {
+            handle(collection_routes::delete_collection(req, ctx))
+        })
+        .post_async("/api/objects", |req, ctx| {
+            handle(object_routes::create_object(req, ctx))
+        })
+        .put_async("/api/objects", |req, ctx| {
+            handle(object_routes::update_object(req, ctx))
+        })
+        .delete_async("/api/objects", |req, ctx| {
+            handle(object_routes::delete_object(req, ctx))
+        })
+        .post_async("/api/objects/update_many", |req, ctx| {
+            handle(object_routes::update_many(req, ctx))
+        })
+        .post_async("/api/objects/delete_many", |req, ctx| {
+            handle(object_routes::delete_many(req, ctx))
+        })
+        .post_async("/api/search", |req, ctx| {
+            handle(search_routes::search_objects(req, ctx))
+        })
+        .post_async("/api/share", |req, ctx| {
+            handle(share_routes::create_share_link(req, ctx))
+        })
         .run(req, env)
-        .await
+        .await?;
+
+    if let Some(token) = token {
+        rate_limit_service::apply_headers(&token, response.headers_mut())?;
+    }
+    Ok(response)
+}
+
+#[event(scheduled)]
+pub async fn scheduled(_event: ScheduledEvent, env: Env, _ctx: ScheduleContext) {
+    if let Err(err) = sweep_service::run(&env).await {
+        console_error!("scheduled sweep failed: {}", err);
+    }
 }
@@ -0,0 +1,272 @@
+use crate::models::error_models::{ApiResult, GistDbError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    Read,
+    Write,
+    Admin,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyScope {
+    pub database: String,
+    #[serde(default)]
+    pub collection: Option<String>,
+    pub permission: Permission,
+}
+
+impl KeyScope {
+    pub fn allows(&self, database: &str, collection: Option<&str>, permission: Permission) -> bool {
+        let database_matches = self.database == "*" || self.database == database;
+        let collection_matches = match (&self.collection, collection) {
+            (None, _) => true,
+            (Some(scoped), _) if scoped == "*" => true,
+            (Some(scoped), Some(requested)) => scoped == requested,
+            (Some(_), None) => false,
+        };
+        database_matches && collection_matches && self.permission >= permission
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub id: String,
+    pub name: String,
+    pub owner: String,
+    pub encrypted_token: String,
+    #[serde(default)]
+    pub role: Option<String>,
+    pub scopes: Vec<KeyScope>,
+    pub created_at: String,
+    #[serde(default)]
+    pub revoked_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PrincipalKind {
+    #[default]
+    GithubToken,
+    ApiKey {
+        key_id: String,
+        role: Option<String>,
+        scopes: Vec<KeyScope>,
+    },
+    ShareLink {
+        database: String,
+        collection: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Principal {
+    #[serde(skip)]
+    pub token: String,
+    pub login: Option<String>,
+    pub scopes: Option<Vec<String>>,
+    #[serde(skip)]
+    pub kind: PrincipalKind,
+}
+
+impl Principal {
+    pub fn github(token: String) -> Self {
+        Principal {
+            token,
+            login: None,
+            scopes: None,
+            kind: PrincipalKind::GithubToken,
+        }
+    }
+
+    pub fn is_github_token(&self) -> bool {
+        matches!(self.kind, PrincipalKind::GithubToken)
+    }
+
+    pub fn enforces_acl(&self) -> bool {
+        matches!(self.kind, PrincipalKind::ApiKey { .. })
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        match &self.kind {
+            PrincipalKind::ApiKey { key_id, .. } => Some(key_id),
+            PrincipalKind::GithubToken => self.login.as_deref(),
+            PrincipalKind::ShareLink { .. } => None,
+        }
+    }
+
+    pub fn role(&self) -> Option<&str> {
+        match &self.kind {
+            PrincipalKind::ApiKey { role, .. } => role.as_deref(),
+            PrincipalKind::GithubToken | PrincipalKind::ShareLink { .. } => None,
+        }
+    }
+
+    pub fn can_access_database(&self, database: &str) -> bool {
+        match &self.kind {
+            PrincipalKind::GithubToken => true,
+            PrincipalKind::ApiKey { scopes, .. } => scopes
+                .iter()
+                .any(|scope| scope.database == "*" || scope.database == database),
+            PrincipalKind::ShareLink {
+                database: shared, ..
+            } => shared == database,
+        }
+    }
+
+    pub fn authorize(
+        &self,
+        database: &str,
+        collection: Option<&str>,
+        permission: Permission,
+    ) -> ApiResult<()> {
+        match &self.kind {
+            PrincipalKind::ApiKey { key_id, scopes, .. } => {
+                if scopes
+                    .iter()
+                    .any(|scope| scope.allows(database, collection, permission))
+                {
+                    Ok(())
+                } else {
+                    Err(GistDbError::Forbidden(format!(
+                        "API key '{}' does not grant {:?} access to this resource",
+                        key_id, permission
+                    )))
+                }
+            }
+            PrincipalKind::ShareLink {
+                database: shared_database,
+                collection: shared_collection,
+            } => {
+                let collection_matches = match (shared_collection, collection) {
+                    (None, _) => true,
+                    (Some(shared), Some(requested)) => shared == requested,
+                    (Some(_), None) => false,
+                };
+                if permission == Permission::Read
+                    && shared_database == database
+                    && collection_matches
+                {
+                    Ok(())
+                } else {
+                    Err(GistDbError::Forbidden(
+                        "Share token only grants read access to the shared resource".to_string(),
+                    ))
+                }
+            }
+            PrincipalKind::GithubToken => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AclAction {
+    Read,
+    Create,
+    Update,
+    Delete,
+    Search,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AclRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub create: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policies: Vec<RowPolicy>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RowPolicy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actions: Option<Vec<AclAction>>,
+    pub filter: Value,
+}
+
+impl RowPolicy {
+    pub fn applies_to(&self, action: AclAction, role: Option<&str>) -> bool {
+        let role_matches = self.roles.as_ref().is_none_or(|roles| {
+            role.is_some_and(|role| roles.iter().any(|r| r == role || r == "*"))
+        });
+        let action_matches = self
+            .actions
+            .as_ref()
+            .is_none_or(|actions| actions.contains(&action));
+        role_matches && action_matches
+    }
+}
+
+impl AclRule {
+    pub fn allows(&self, action: AclAction, role: Option<&str>) -> bool {
+        let roles = match action {
+            AclAction::Read => &self.read,
+            AclAction::Create => &self.create,
+            AclAction::Update => &self.update,
+            AclAction::Delete => &self.delete,
+            AclAction::Search => &self.search,
+        };
+        match roles {
+            None => true,
+            Some(roles) => role.is_some_and(|role| roles.iter().any(|r| r == role || r == "*")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatabaseAcl {
+    #[serde(default)]
+    pub collections: HashMap<String, AclRule>,
+}
+
+impl DatabaseAcl {
+    pub fn rule(&self, collection: &str) -> Option<&AclRule> {
+        self.collections
+            .get(collection)
+            .or_else(|| self.collections.get("*"))
+    }
+
+    pub fn allows(&self, collection: Option<&str>, action: AclAction, role: Option<&str>) -> bool {
+        match collection {
+            Some(name) => self.rule(name).is_none_or(|rule| rule.allows(action, role)),
+            None => self
+                .collections
+                .values()
+                .all(|rule| rule.allows(action, role)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Operation {
+    pub database: String,
+    pub collection: Option<String>,
+    pub permission: Permission,
+    pub action: Option<AclAction>,
+}
+
+#[derive(Debug, Default)]
+pub struct AuthContext {
+    pub principal: Option<Principal>,
+}
+
+impl AuthContext {
+    pub fn principal(&self) -> ApiResult<&Principal> {
+        self.principal
+            .as_ref()
+            .ok_or_else(|| GistDbError::Unauthorized("Authorization header required".to_string()))
+    }
+}
@@ -0,0 +1,19 @@
+use crate::models::manifest_models::Manifest;
+use crate::models::trash_models::Trash;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+pub const BUNDLE_FORMAT: &str = "gistdb-bundle";
+pub const BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    pub format: String,
+    pub bundle_version: u32,
+    pub exported_at: String,
+    pub manifest: Manifest,
+    pub collections: BTreeMap<String, Map<String, Value>>,
+    #[serde(default)]
+    pub trash: Trash,
+}
@@ -0,0 +1,136 @@
+use serde_json::{json, Value};
+use std::fmt;
+
+pub type ApiResult<T> = std::result::Result<T, GistDbError>;
+
+#[derive(Debug)]
+pub enum GistDbError {
+    BadRequest(String),
+    NotFound(String),
+    Unauthorized(String),
+    Forbidden(String),
+    Conflict {
+        message: String,
+        details: Option<Value>,
+    },
+    Validation {
+        message: String,
+        details: Option<Value>,
+    },
+    Upstream {
+        status: u16,
+        message: String,
+    },
+    RateLimited {
+        retry_after: Option<u64>,
+    },
+    Internal(String),
+}
+
+impl GistDbError {
+    pub fn conflict(message: impl Into<String>) -> Self {
+        GistDbError::Conflict {
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn at_row(self, row: usize) -> Self {
+        self.within(&format!("Row {}", row))
+    }
+
+    /// Prefixes client errors with where they happened, e.g. `Row 3: ...`.
+    pub fn within(self, context: &str) -> Self {
+        match self {
+            GistDbError::BadRequest(message) => {
+                GistDbError::BadRequest(format!("{}: {}", context, message))
+            }
+            GistDbError::Conflict { message, details } => GistDbError::Conflict {
+                message: format!("{}: {}", context, message),
+                details,
+            },
+            GistDbError::Validation { message, details } => GistDbError::Validation {
+                message: format!("{}: {}", context, message),
+                details,
+            },
+            other => other,
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        match self {
+            GistDbError::BadRequest(_) => 400,
+            GistDbError::Unauthorized(_) => 401,
+            GistDbError::Forbidden(_) => 403,
+            GistDbError::NotFound(_) => 404,
+            GistDbError::Conflict { .. } => 409,
+            GistDbError::Validation { .. } => 422,
+            GistDbError::RateLimited { .. } => 429,
+            GistDbError::Internal(_) => 500,
+            GistDbError::Upstream { .. } => 502,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            GistDbError::BadRequest(_) => "bad_request",
+            GistDbError::Unauthorized(_) => "unauthorized",
+            GistDbError::Forbidden(_) => "forbidden",
+            GistDbError::NotFound(_) => "not_found",
+            GistDbError::Conflict { .. } => "conflict",
+            GistDbError::Validation { .. } => "validation_failed",
+            GistDbError::RateLimited { .. } => "rate_limited",
+            GistDbError::Internal(_) => "internal_error",
+            GistDbError::Upstream { .. } => "upstream_error",
+        }
+    }
+
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            GistDbError::Conflict { details, .. } | GistDbError::Validation { details, .. } => {
+                details.clone()
+            }
+            GistDbError::Upstream { status, .. } => Some(json!({ "github_status": status })),
+            GistDbError::RateLimited { retry_after } => {
+                retry_after.map(|secs| json!({ "retry_after": secs }))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GistDbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GistDbError::BadRequest(message)
+            | GistDbError::NotFound(message)
+            | GistDbError::Unauthorized(message)
+            | GistDbError::Forbidden(message)
+            | GistDbError::Internal(message)
+            | GistDbError::Conflict { message, .. }
+            | GistDbError::Validation { message, .. }
+            | GistDbError::Upstream { message, .. } => write!(f, "{}", message),
+            GistDbError::RateLimited { .. } => write!(f, "GitHub API rate limit exceeded"),
+        }
+    }
+}
+
+impl std::error::Error for GistDbError {}
+
+impl From<worker::Error> for GistDbError {
+    fn from(err: worker::Error) -> Self {
+        GistDbError::Internal(err.to_string())
+    }
+}
+
+impl From<worker::kv::KvError> for GistDbError {
+    fn from(err: worker::kv::KvError) -> Self {
+        GistDbError::Internal(format!("KV error: {}", err))
+    }
+}
+
+impl From<serde_json::Error> for GistDbError {
+    fn from(err: serde_json::Error) -> Self {
+        GistDbError::Internal(format!("JSON error: {}", err))
+    }
+}
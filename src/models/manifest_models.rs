@@ -0,0 +1,202 @@
+use crate::utils::filter::{get_path, remove_path, set_path};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+pub const MANIFEST_FILE: &str = "_gistdb.json";
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CollectionSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub defaults: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub computed: Vec<ComputedField>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub soft_delete: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<TtlSettings>,
+}
+
+pub const EXPIRES_AT_FIELD: &str = "expires_at";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TtlSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_seconds: Option<u64>,
+}
+
+pub fn expiry_of(object: &Value) -> Option<DateTime<Utc>> {
+    match object.get(EXPIRES_AT_FIELD)? {
+        Value::String(s) => DateTime::parse_from_rfc3339(s)
+            .ok()
+            .map(|t| t.with_timezone(&Utc)),
+        Value::Number(n) => DateTime::from_timestamp(n.as_i64()?, 0),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transform {
+    Lowercase,
+    Uppercase,
+    Trim,
+    Slug,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComputedField {
+    pub field: String,
+    pub from: String,
+    pub transform: Transform,
+}
+
+fn slugify(s: &str) -> String {
+    s.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+impl Transform {
+    pub fn apply(self, value: &str) -> String {
+        match self {
+            Transform::Lowercase => value.to_lowercase(),
+            Transform::Uppercase => value.to_uppercase(),
+            Transform::Trim => value.trim().to_string(),
+            Transform::Slug => slugify(value),
+        }
+    }
+}
+
+impl CollectionSettings {
+    /// Only collections with expiry enabled treat `expires_at` as an expiry;
+    /// elsewhere it is an ordinary field.
+    pub fn is_expired(&self, object: &Value, now: DateTime<Utc>) -> bool {
+        self.ttl.is_some() && expiry_of(object).is_some_and(|expires_at| expires_at <= now)
+    }
+
+    pub fn prepare(&self, object: &mut Value) {
+        if !object.is_object() {
+            return;
+        }
+        if let Some(seconds) = self.ttl.as_ref().and_then(|ttl| ttl.default_seconds) {
+            if object.get(EXPIRES_AT_FIELD).is_none_or(Value::is_null) {
+                let expires_at = Utc::now() + Duration::seconds(seconds as i64);
+                set_path(
+                    object,
+                    EXPIRES_AT_FIELD,
+                    Value::String(expires_at.to_rfc3339()),
+                );
+            }
+        }
+        for (path, default) in &self.defaults {
+            if get_path(object, path).is_none_or(Value::is_null) {
+                set_path(object, path, default.clone());
+            }
+        }
+        for computed in &self.computed {
+            match get_path(object, &computed.from).and_then(Value::as_str) {
+                Some(source) => {
+                    let value = Value::String(computed.transform.apply(source));
+                    set_path(object, &computed.field, value);
+                }
+                None => remove_path(object, &computed.field),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexDefinition {
+    pub name: String,
+    pub collection: String,
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub unique: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnDelete {
+    Restrict,
+    Cascade,
+    SetNull,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Relation {
+    pub name: String,
+    pub collection: String,
+    pub field: String,
+    pub references: String,
+    pub on_delete: OnDelete,
+}
+
+impl Relation {
+    pub fn refers_to(&self, object: &Value, id: &str) -> bool {
+        match get_path(object, &self.field) {
+            Some(Value::String(s)) => s == id,
+            Some(Value::Array(items)) => items.iter().any(|item| item.as_str() == Some(id)),
+            _ => false,
+        }
+    }
+
+    pub fn clear_reference(&self, object: &mut Value, id: &str) {
+        let cleared = match get_path(object, &self.field) {
+            Some(Value::Array(items)) => Value::Array(
+                items
+                    .iter()
+                    .filter(|item| item.as_str() != Some(id))
+                    .cloned()
+                    .collect(),
+            ),
+            _ => Value::Null,
+        };
+        set_path(object, &self.field, cleared);
+    }
+}
+
+impl IndexDefinition {
+    pub fn key(&self, object: &Value) -> Option<Vec<Value>> {
+        self.fields
+            .iter()
+            .map(|field| get_path(object, field).filter(|v| !v.is_null()).cloned())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    pub name: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub collections: BTreeMap<String, CollectionSettings>,
+    #[serde(default)]
+    pub indexes: Vec<IndexDefinition>,
+    #[serde(default)]
+    pub relations: Vec<Relation>,
+}
+
+impl Manifest {
+    pub fn new(name: &str) -> Self {
+        Manifest {
+            format_version: FORMAT_VERSION,
+            name: name.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            collections: BTreeMap::new(),
+            indexes: Vec::new(),
+            relations: Vec::new(),
+        }
+    }
+}
@@ -1,2 +1,7 @@
+pub mod auth_models;
+pub mod bundle_models;
+pub mod error_models;
+pub mod manifest_models;
 pub mod request_models;
 pub mod response_models;
+pub mod trash_models;
@@ -1,5 +1,7 @@
+use crate::models::auth_models::KeyScope;
+use crate::models::manifest_models::{ComputedField, OnDelete};
 use serde::Deserialize;
-use serde_json::Value;
+use serde_json::{Map, Value};
 
 #[derive(Debug, Deserialize)]
 pub struct CreateDatabaseRequest {
@@ -19,17 +21,13 @@ pub struct CreateObjectRequest {
     pub data: Value,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct GetDatabaseRequest {
-    pub _gist_id: String,
-}
-
 #[derive(Debug, Deserialize)]
 pub struct UpdateObjectRequest {
     pub gist_id: String,
     pub collection_name: String,
     pub object_id: String,
-    pub data: Value,
+    pub data: Option<Value>,
+    pub update: Option<Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -39,6 +37,103 @@ pub struct DeleteObjectRequest {
     pub object_id: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SetSchemaRequest {
+    pub gist_id: String,
+    pub collection_name: String,
+    pub schema: Option<Value>,
+    #[serde(default)]
+    pub validate_existing: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetFieldsRequest {
+    pub gist_id: String,
+    pub collection_name: String,
+    #[serde(default)]
+    pub defaults: Map<String, Value>,
+    #[serde(default)]
+    pub computed: Vec<ComputedField>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetSoftDeleteRequest {
+    pub gist_id: String,
+    pub collection_name: String,
+    pub soft_delete: bool,
+    pub retention_days: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetTtlRequest {
+    pub gist_id: String,
+    pub collection_name: String,
+    pub enabled: bool,
+    pub default_ttl_seconds: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreRequest {
+    pub gist_id: String,
+    pub collection_name: String,
+    pub object_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PurgeTrashRequest {
+    pub gist_id: String,
+    pub collection_name: Option<String>,
+    pub object_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateIndexRequest {
+    pub gist_id: String,
+    pub collection_name: String,
+    pub name: Option<String>,
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub unique: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteIndexRequest {
+    pub gist_id: String,
+    pub collection_name: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRelationRequest {
+    pub gist_id: String,
+    pub name: Option<String>,
+    pub collection_name: String,
+    pub field: String,
+    pub references: String,
+    pub on_delete: Option<OnDelete>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteRelationRequest {
+    pub gist_id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateManyRequest {
+    pub gist_id: String,
+    pub collection_name: String,
+    pub filter: Value,
+    pub update: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteManyRequest {
+    pub gist_id: String,
+    pub collection_name: String,
+    pub filter: Value,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DeleteCollectionRequest {
     pub gist_id: String,
@@ -50,11 +145,44 @@ pub struct DeleteDatabaseRequest {
     pub gist_id: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RollbackRequest {
+    pub version: String,
+    pub collections: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Lookup {
+    pub field: String,
+    pub from: String,
+    pub foreign_field: Option<String>,
+    #[serde(rename = "as")]
+    pub as_field: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SearchRequest {
     pub gist_id: String,
     pub collection_name: String,
-    pub query: String,
+    pub query: Option<String>,
     pub field: Option<String>,
+    pub filter: Option<Value>,
+    #[serde(default)]
+    pub lookup: Vec<Lookup>,
+    pub at_revision: Option<String>,
     // pub limit: Option<usize>,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub role: Option<String>,
+    pub scopes: Vec<KeyScope>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateShareLinkRequest {
+    pub gist_id: String,
+    pub collection_name: Option<String>,
+    pub expires_in: Option<u64>,
+}
@@ -7,4 +7,8 @@ pub struct ApiResponse {
     pub data: Option<Value>,
     pub message: String,
     pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
 }
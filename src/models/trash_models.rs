@@ -0,0 +1,75 @@
+use crate::models::manifest_models::{CollectionSettings, IndexDefinition};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+pub const TRASH_FILE: &str = "_deleted.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedObject {
+    pub deleted_at: String,
+    pub data: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedCollection {
+    pub deleted_at: String,
+    pub settings: CollectionSettings,
+    #[serde(default)]
+    pub indexes: Vec<IndexDefinition>,
+    pub objects: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Trash {
+    #[serde(default)]
+    pub objects: BTreeMap<String, BTreeMap<String, TrashedObject>>,
+    #[serde(default)]
+    pub collections: BTreeMap<String, TrashedCollection>,
+}
+
+pub fn is_expired(deleted_at: &str, retention_days: Option<u32>, now: DateTime<Utc>) -> bool {
+    let Some(days) = retention_days else {
+        return false;
+    };
+    DateTime::parse_from_rfc3339(deleted_at)
+        .is_ok_and(|deleted| deleted.with_timezone(&Utc) + Duration::days(days.into()) <= now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(timestamp)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn never_expires_without_retention() {
+        assert!(!is_expired(
+            "2000-01-01T00:00:00Z",
+            None,
+            at("2030-01-01T00:00:00Z")
+        ));
+    }
+
+    #[test]
+    fn expires_once_retention_has_passed() {
+        let deleted_at = "2025-01-01T00:00:00Z";
+        assert!(!is_expired(deleted_at, Some(7), at("2025-01-07T23:59:59Z")));
+        assert!(is_expired(deleted_at, Some(7), at("2025-01-08T00:00:00Z")));
+        assert!(is_expired(deleted_at, Some(0), at("2025-01-01T00:00:00Z")));
+    }
+
+    #[test]
+    fn unparseable_deletion_time_is_kept() {
+        assert!(!is_expired(
+            "yesterday",
+            Some(1),
+            at("2030-01-01T00:00:00Z")
+        ));
+    }
+}
@@ -0,0 +1,31 @@
+use crate::models::auth_models::{AuthContext, DatabaseAcl};
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::services::acl_service;
+use crate::utils::api_response::api_response;
+use crate::utils::request::parse_body;
+use worker::{Request, Response, RouteContext};
+
+fn gist_id(ctx: &RouteContext<AuthContext>) -> ApiResult<&String> {
+    ctx.param("gistId")
+        .ok_or_else(|| GistDbError::BadRequest("Missing gist ID".to_string()))
+}
+
+pub async fn get_acl(_req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let gist_id = gist_id(&ctx)?;
+    acl_service::require_owner(ctx.data.principal()?, gist_id).await?;
+    let acl = acl_service::load_acl(&ctx.env, gist_id).await?;
+    api_response(200, Some(serde_json::to_value(acl)?), "Access rules", "")
+}
+
+pub async fn put_acl(mut req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let gist_id = gist_id(&ctx)?;
+    let acl: DatabaseAcl = parse_body(&mut req).await?;
+    acl_service::require_owner(ctx.data.principal()?, gist_id).await?;
+    acl_service::save_acl(&ctx.env, gist_id, &acl).await?;
+    api_response(
+        200,
+        Some(serde_json::to_value(acl)?),
+        "Access rules updated",
+        "",
+    )
+}
@@ -1,157 +1,376 @@
-use crate::models::request_models::{CreateCollectionRequest, DeleteCollectionRequest};
-use crate::services::github_service::github_request;
+use crate::models::auth_models::{AclAction, AuthContext, DatabaseAcl};
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::models::manifest_models::TtlSettings;
+use crate::models::request_models::{
+    CreateCollectionRequest, DeleteCollectionRequest, SetFieldsRequest, SetSchemaRequest,
+    SetSoftDeleteRequest, SetTtlRequest,
+};
+use crate::services::database_service::Database;
+use crate::services::{acl_service, lookup_service, sweep_service};
 use crate::utils::api_response::api_response;
+use crate::utils::records::{self, RecordFormat};
+use crate::utils::request::parse_body;
+use crate::utils::schema::Schema;
 use serde_json::{json, Map, Value};
 use std::collections::HashMap;
-use worker::{Method, Request, Response, Result, RouteContext};
+use worker::{Error, Headers, Request, Response, Result, RouteContext};
 
-pub async fn create_collection(mut req: Request, _ctx: RouteContext<()>) -> Result<Response> {
-    let token = match req.headers().get("Authorization")? {
-        Some(h) => h.replace("Bearer ", ""),
-        None => return api_response(401, None, "", "Authorization header required"),
-    };
-
-    let payload: CreateCollectionRequest = match req.json().await {
-        Ok(p) => p,
-        Err(_) => return api_response(400, None, "", "Invalid request body"),
-    };
-
-    let filename = format!("{}.json", payload.name);
-    let gist_id = payload.gist_id;
-
-    let existing = github_request(
-        &token,
-        Method::Get,
-        &format!("https://api.github.com/gists/{}", gist_id),
-        None,
-    )
-    .await
-    .map_err(|e| e.to_string())?;
+pub async fn create_collection(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: CreateCollectionRequest = parse_body(&mut req).await?;
 
-    if existing["files"][&filename].is_object() {
-        return api_response(409, None, "", "Collection already exists");
-    }
-
-    let description = existing["description"]
-        .as_str()
-        .unwrap_or("GistDB Database");
-    let update_body = json!({
-        "description": description,
-        "files": { filename: { "content": "{}" } }
-    });
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    db.add_collection(&payload.name)?;
+    db.commit(token).await?;
 
-    match github_request(
-        &token,
-        Method::Patch,
-        &format!("https://api.github.com/gists/{}", gist_id),
-        Some(update_body),
+    api_response(
+        201,
+        Some(json!({ "collection_name": payload.name })),
+        "Collection created",
+        "",
     )
-    .await
-    {
-        Ok(_) => api_response(
-            201,
-            Some(json!({ "collection_name": payload.name })),
-            "Collection created",
-            "",
-        ),
-        Err(e) => api_response(500, None, "", &e.to_string()),
-    }
 }
 
-pub async fn get_collection(req: Request, ctx: RouteContext<()>) -> Result<Response> {
-    let token = match req.headers().get("Authorization")? {
-        Some(h) => h.replace("Bearer ", ""),
-        None => return api_response(401, None, "", "Authorization header required"),
-    };
+pub async fn get_collection(req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let principal = ctx.data.principal()?;
+    let token = &principal.token;
 
-    let gist_id = match ctx.param("gistId") {
-        Some(id) => id,
-        None => return api_response(400, None, "", "Missing gist ID"),
-    };
+    let gist_id = ctx
+        .param("gistId")
+        .ok_or_else(|| GistDbError::BadRequest("Missing gist ID".to_string()))?;
 
     let url = req.url()?;
     let query_params: HashMap<_, _> = url.query_pairs().into_owned().collect();
     let collection_name = query_params.get("collection_name").map(|s| s.to_string());
+    let object_id = query_params.get("object_id");
+    let lookups = query_params
+        .get("lookup")
+        .map(|param| lookup_service::parse_param(param))
+        .transpose()?
+        .unwrap_or_default();
+    if collection_name.is_none() && (object_id.is_some() || !lookups.is_empty()) {
+        return Err(GistDbError::BadRequest(
+            "'object_id' and 'lookup' require 'collection_name'".to_string(),
+        ));
+    }
+    let lookups = lookup_service::plan(&ctx.env, principal, gist_id, lookups).await?;
 
-    let gist = github_request(
-        &token,
-        Method::Get,
-        &format!("https://api.github.com/gists/{}", gist_id),
-        None,
-    )
-    .await
-    .map_err(|e| e.to_string())?;
+    let at_revision = query_params.get("at_revision").map(String::as_str);
+    let db = Database::load_at(token, gist_id, at_revision).await?;
+    let acl = if !principal.enforces_acl() {
+        DatabaseAcl::default()
+    } else {
+        acl_service::load_acl(&ctx.env, gist_id).await?
+    };
 
     if let Some(collection_name) = collection_name {
-        let filename = format!("{}.json", collection_name);
-        match gist["files"].get(&filename) {
-            Some(file) => {
-                let content = file["content"].as_str().unwrap_or("{}");
-                let data: Value = serde_json::from_str(content)?;
-                api_response(200, Some(data), "Collection contents", "")
-            }
-            None => api_response(404, None, "", "Collection not found"),
+        let mut data = db.collection(&collection_name)?;
+        if let Some(filter) =
+            acl_service::row_filter(&acl, principal, &collection_name, AclAction::Read)?
+        {
+            data.retain(|_, object| filter.matches(object));
+        }
+
+        if let Some(object_id) = object_id {
+            let mut object = data
+                .remove(object_id)
+                .ok_or_else(|| GistDbError::NotFound("Object not found".to_string()))?;
+            lookup_service::resolve(&db, &lookups, [&mut object])?;
+            return api_response(
+                200,
+                Some(json!({ "object_id": object_id, "data": object })),
+                "Object",
+                "",
+            );
         }
+
+        lookup_service::resolve(&db, &lookups, data.values_mut())?;
+        api_response(200, Some(Value::Object(data)), "Collection contents", "")
     } else {
         let mut result = Map::new();
-        for (filename, file) in gist["files"].as_object().unwrap_or(&Map::new()) {
-            let content = file["content"].as_str().unwrap_or("{}");
-            result.insert(
-                filename.clone(),
-                serde_json::from_str(content).unwrap_or(Value::Null),
-            );
+        for collection_name in db.collection_names() {
+            let mut data = db.collection(&collection_name)?;
+            if let Some(filter) =
+                acl_service::row_filter(&acl, principal, &collection_name, AclAction::Read)?
+            {
+                data.retain(|_, object| filter.matches(object));
+            }
+            result.insert(collection_name, Value::Object(data));
         }
         api_response(200, Some(Value::Object(result)), "Database contents", "")
     }
 }
 
-pub async fn delete_collection(mut req: Request, _ctx: RouteContext<()>) -> Result<Response> {
-    let token = match req.headers().get("Authorization")? {
-        Some(h) => h.replace("Bearer ", ""),
-        None => return api_response(401, None, "", "Authorization header required"),
-    };
-
-    let payload: DeleteCollectionRequest = match req.json().await {
-        Ok(p) => p,
-        Err(_) => return api_response(400, None, "", "Invalid request body"),
-    };
+pub async fn delete_collection(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: DeleteCollectionRequest = parse_body(&mut req).await?;
 
-    let filename = format!("{}.json", payload.collection_name);
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    let changes = db.remove_collection(&payload.collection_name)?;
+    db.commit(token).await?;
 
-    let gist = github_request(
-        &token,
-        Method::Get,
-        &format!("https://api.github.com/gists/{}", payload.gist_id),
-        None,
+    api_response(
+        200,
+        Some(json!({ "deleted_collection": payload.collection_name, "changes": changes })),
+        "Collection deleted",
+        "",
     )
-    .await?;
+}
+
+pub async fn set_schema(mut req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: SetSchemaRequest = parse_body(&mut req).await?;
 
-    if let Some(files) = gist["files"].as_object() {
-        if !files.contains_key(&filename) {
-            return api_response(404, None, "", "Collection not found");
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    if let (Some(schema), true) = (&payload.schema, payload.validate_existing) {
+        let schema = Schema::parse(schema)?;
+        let invalid: Vec<Value> = db
+            .collection(&payload.collection_name)?
+            .iter()
+            .filter_map(|(object_id, object)| {
+                let violations = schema.validate(object);
+                (!violations.is_empty())
+                    .then(|| json!({ "object_id": object_id, "violations": violations }))
+            })
+            .collect();
+        if !invalid.is_empty() {
+            return Err(GistDbError::Validation {
+                message: format!(
+                    "{} existing object(s) do not match the new schema",
+                    invalid.len()
+                ),
+                details: Some(json!({ "objects": invalid })),
+            });
         }
-    } else {
-        return api_response(500, None, "", "Internal Server Error");
     }
+    db.set_schema(&payload.collection_name, payload.schema.clone())?;
+    db.commit(token).await?;
+
+    api_response(
+        200,
+        Some(json!({
+            "collection_name": payload.collection_name,
+            "schema": payload.schema,
+        })),
+        "Collection schema updated",
+        "",
+    )
+}
+
+pub async fn set_fields(mut req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: SetFieldsRequest = parse_body(&mut req).await?;
+
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    db.set_fields(
+        &payload.collection_name,
+        payload.defaults.clone(),
+        payload.computed.clone(),
+    )?;
+    db.commit(token).await?;
+
+    api_response(
+        200,
+        Some(json!({
+            "collection_name": payload.collection_name,
+            "defaults": payload.defaults,
+            "computed": payload.computed,
+        })),
+        "Collection fields updated",
+        "",
+    )
+}
+
+pub async fn set_soft_delete(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: SetSoftDeleteRequest = parse_body(&mut req).await?;
+
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    db.set_soft_delete(
+        &payload.collection_name,
+        payload.soft_delete,
+        payload.retention_days,
+    )?;
+    if payload.retention_days.is_some() {
+        sweep_service::register(&ctx.env, token, &payload.gist_id).await?;
+    }
+    db.commit(token).await?;
+
+    api_response(
+        200,
+        Some(json!({
+            "collection_name": payload.collection_name,
+            "soft_delete": payload.soft_delete,
+            "retention_days": payload.retention_days,
+        })),
+        "Collection soft delete updated",
+        "",
+    )
+}
 
-    let description = gist["description"].as_str().unwrap_or("GistDB Database");
-    let body = json!({
-        "description": description,
-        "files": { filename: null }
+pub async fn set_ttl(mut req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: SetTtlRequest = parse_body(&mut req).await?;
+
+    let ttl = payload.enabled.then_some(TtlSettings {
+        default_seconds: payload.default_ttl_seconds,
     });
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    db.set_ttl(&payload.collection_name, ttl.clone())?;
+    // Registered first: a registration without expiry costs one empty sweep,
+    // while a committed TTL without one would never be swept.
+    if ttl.is_some() {
+        sweep_service::register(&ctx.env, token, &payload.gist_id).await?;
+    }
+    db.commit(token).await?;
+
+    api_response(
+        200,
+        Some(json!({
+            "collection_name": payload.collection_name,
+            "ttl": ttl,
+        })),
+        "Collection TTL updated",
+        "",
+    )
+}
+
+const EXPORT_CHUNK_ROWS: usize = 500;
+
+pub async fn export_collection(
+    req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let principal = ctx.data.principal()?;
+    let token = &principal.token;
+    let (Some(gist_id), Some(collection_name)) = (ctx.param("gistId"), ctx.param("collectionName"))
+    else {
+        return Err(GistDbError::BadRequest(
+            "Missing gist ID or collection name".to_string(),
+        ));
+    };
+
+    let url = req.url()?;
+    let query_params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+    let format = RecordFormat::parse(query_params.get("format").map(String::as_str))?;
 
-    github_request(
-        &token,
-        Method::Patch,
-        &format!("https://api.github.com/gists/{}", payload.gist_id),
-        Some(body),
+    let row_filter = acl_service::load_row_filter(
+        &ctx.env,
+        principal,
+        gist_id,
+        collection_name,
+        AclAction::Read,
     )
     .await?;
+    let db = Database::load(token, gist_id).await?;
+    let rows: Vec<(String, Value)> = db
+        .collection(collection_name)?
+        .into_iter()
+        .filter(|(_, object)| row_filter.as_ref().is_none_or(|f| f.matches(object)))
+        .collect();
+
+    let columns = match query_params.get("columns") {
+        Some(columns) => columns
+            .split(',')
+            .filter(|path| !path.is_empty() && *path != records::ID_FIELD)
+            .map(str::to_string)
+            .collect(),
+        None => records::columns(rows.iter().map(|(_, object)| object)),
+    };
+    let mut chunks: Vec<Vec<(String, Value)>> =
+        rows.chunks(EXPORT_CHUNK_ROWS).map(<[_]>::to_vec).collect();
+    if chunks.is_empty() && format == RecordFormat::Csv {
+        chunks.push(Vec::new());
+    }
+
+    let stream = futures_util::stream::iter(chunks.into_iter().enumerate().map(
+        move |(i, chunk)| -> Result<Vec<u8>> {
+            let bytes = match format {
+                RecordFormat::Ndjson => chunk
+                    .iter()
+                    .map(|(id, object)| records::ndjson_line(id, object))
+                    .collect::<ApiResult<String>>()
+                    .map(String::into_bytes),
+                RecordFormat::Csv => records::csv_chunk(&columns, &chunk, i == 0),
+            };
+            bytes.map_err(|e| Error::RustError(e.to_string()))
+        },
+    ));
+
+    let mut headers = Headers::new();
+    headers.set("Content-Type", format.content_type())?;
+    headers.set(
+        "Content-Disposition",
+        &format!(
+            "attachment; filename=\"{}.{}\"",
+            collection_name,
+            format.extension()
+        ),
+    )?;
+    Ok(Response::from_stream(stream)?.with_headers(headers))
+}
+
+pub async fn import_collection(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let (Some(gist_id), Some(collection_name)) = (ctx.param("gistId"), ctx.param("collectionName"))
+    else {
+        return Err(GistDbError::BadRequest(
+            "Missing gist ID or collection name".to_string(),
+        ));
+    };
+
+    let url = req.url()?;
+    let query_params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+    let format = RecordFormat::parse(query_params.get("format").map(String::as_str))?;
+
+    let infer_types = query_params.get("infer_types").is_some_and(|v| v == "true");
+
+    let body = req.text().await?;
+    let mut db = Database::load(token, gist_id).await?;
+    let rows = match format {
+        RecordFormat::Ndjson => records::parse_ndjson(&body)?,
+        RecordFormat::Csv => {
+            let schema = db
+                .require_collection(collection_name)?
+                .schema
+                .as_ref()
+                .map(Schema::parse)
+                .transpose()?;
+            records::parse_csv(&body, schema.as_ref(), infer_types)?
+        }
+    };
+
+    let mut data = db.collection(collection_name)?;
+
+    // The collection is a single gist file, so all rows go out in one commit:
+    // either every row is imported or none is.
+    let object_ids = db.insert_rows(collection_name, &mut data, rows)?;
+
+    if !object_ids.is_empty() {
+        db.put_collection(collection_name, &data)?;
+        db.commit(token).await?;
+    }
 
     api_response(
-        200,
-        Some(json!({ "deleted_collection": payload.collection_name })),
-        "Collection deleted",
+        201,
+        Some(json!({
+            "collection_name": collection_name,
+            "imported": object_ids.len(),
+            "object_ids": object_ids,
+        })),
+        "Objects imported",
         "",
     )
 }
@@ -1,73 +1,283 @@
-use crate::models::request_models::{CreateDatabaseRequest, DeleteDatabaseRequest};
-use crate::services::github_service::github_request;
+use crate::models::auth_models::{AclAction, AuthContext};
+use crate::models::bundle_models::Bundle;
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::models::request_models::{
+    CreateDatabaseRequest, DeleteDatabaseRequest, RollbackRequest,
+};
+use crate::services::database_service::{self, Database};
+use crate::services::github_service::delete_gist;
+use crate::services::{acl_service, history_service, migration_service, sweep_service};
 use crate::utils::api_response::api_response;
-use serde_json::json;
-use worker::{Method, Request, Response, Result, RouteContext};
-
-pub async fn create_database(mut req: Request, _ctx: RouteContext<()>) -> Result<Response> {
-    let headers = req.headers();
-    let token = headers
-        .get("Authorization")?
-        .unwrap_or_default()
-        .replace("Bearer ", "");
-
-    let payload: CreateDatabaseRequest = match req.json().await {
-        Ok(p) => p,
-        Err(_) => return api_response(400, None, "", "Invalid request body"),
-    };
+use crate::utils::request::parse_body;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use worker::{console_error, Headers, Request, Response, RouteContext};
 
-    let filename = format!("{}.json", payload.name);
-    let body = json!({
-        "description": payload.name,
-        "public": false,
-        "files": {
-            filename: {
-                "content": "{}"
-            }
-        }
-    });
+pub async fn create_database(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: CreateDatabaseRequest = parse_body(&mut req).await?;
 
-    match github_request(
-        &token,
-        Method::Post,
-        "https://api.github.com/gists",
-        Some(body),
+    let (gist_id, manifest) = database_service::create_database(token, &payload.name).await?;
+
+    api_response(
+        201,
+        Some(json!({
+            "gist_id": gist_id,
+            "name": manifest.name,
+            "format_version": manifest.format_version,
+        })),
+        "Database initialized",
+        "",
     )
-    .await
-    {
-        Ok(res) => api_response(
-            201,
-            Some(json!({ "gist_id": res["id"], "collection_name": payload.name })),
-            "Database initialized",
-            "",
-        ),
-        Err(e) => api_response(500, None, "", &e.to_string()),
-    }
 }
 
-pub async fn delete_database(mut req: Request, _ctx: RouteContext<()>) -> Result<Response> {
-    let token = match req.headers().get("Authorization")? {
-        Some(h) => h.replace("Bearer ", ""),
-        None => return api_response(401, None, "", "Authorization header required"),
+pub async fn list_databases(_req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let principal = ctx.data.principal()?;
+
+    let databases: Vec<Value> = database_service::list_databases(&principal.token)
+        .await?
+        .into_iter()
+        .filter(|db| {
+            db["gist_id"]
+                .as_str()
+                .is_some_and(|id| principal.can_access_database(id))
+        })
+        .collect();
+
+    api_response(200, Some(Value::Array(databases)), "Databases", "")
+}
+
+pub async fn delete_database(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: DeleteDatabaseRequest = parse_body(&mut req).await?;
+
+    delete_gist(token, &payload.gist_id).await?;
+
+    api_response(
+        200,
+        Some(json!({ "deleted_gist": payload.gist_id })),
+        "Database deleted",
+        "",
+    )
+}
+
+pub async fn migrate_database(req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let gist_id = ctx
+        .param("gistId")
+        .ok_or_else(|| GistDbError::BadRequest("Missing gist ID".to_string()))?;
+    let dry_run = req
+        .url()?
+        .query_pairs()
+        .any(|(key, value)| key == "dry_run" && value != "false");
+
+    let mut db = Database::load(token, gist_id).await?;
+    let from_version = db.manifest.format_version;
+    let steps = migration_service::migrate(&mut db)?;
+    if !dry_run && !steps.is_empty() {
+        db.commit(token).await?;
+    }
+
+    let message = match (steps.is_empty(), dry_run) {
+        (true, _) => "Database is up to date",
+        (false, true) => "Migration planned",
+        (false, false) => "Database migrated",
     };
+    api_response(
+        200,
+        Some(json!({
+            "gist_id": gist_id,
+            "from_version": from_version,
+            "to_version": db.manifest.format_version,
+            "dry_run": dry_run,
+            "steps": steps,
+        })),
+        message,
+        "",
+    )
+}
+
+pub async fn sweep_database(_req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let gist_id = ctx
+        .param("gistId")
+        .ok_or_else(|| GistDbError::BadRequest("Missing gist ID".to_string()))?;
+
+    let result = sweep_service::sweep_database(token, gist_id).await?;
+    api_response(200, Some(result), "Database swept", "")
+}
 
-    let payload: DeleteDatabaseRequest = match req.json().await {
-        Ok(p) => p,
-        Err(_) => return api_response(400, None, "", "Invalid request body"),
+fn page_params(query_params: &HashMap<String, String>, default: u32, max: u32) -> (u32, u32) {
+    let page = query_params
+        .get("page")
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(1u32)
+        .max(1);
+    let per_page = query_params
+        .get("per_page")
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(default)
+        .clamp(1, max);
+    (page, per_page)
+}
+
+pub async fn list_revisions(req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let gist_id = ctx
+        .param("gistId")
+        .ok_or_else(|| GistDbError::BadRequest("Missing gist ID".to_string()))?;
+
+    let url = req.url()?;
+    let query_params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+    let (page, per_page) = page_params(&query_params, 30, 100);
+
+    let revisions = database_service::list_revisions(token, gist_id, page, per_page).await?;
+    api_response(200, Some(Value::Array(revisions)), "Revisions", "")
+}
+
+pub async fn object_history(req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let principal = ctx.data.principal()?;
+    let token = &principal.token;
+    let gist_id = ctx
+        .param("gistId")
+        .ok_or_else(|| GistDbError::BadRequest("Missing gist ID".to_string()))?;
+
+    let url = req.url()?;
+    let query_params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+    let (Some(collection_name), Some(object_id)) = (
+        query_params.get("collection_name"),
+        query_params.get("object_id"),
+    ) else {
+        return Err(GistDbError::BadRequest(
+            "'collection_name' and 'object_id' are required".to_string(),
+        ));
     };
+    // Every revision on the page is a separate gist fetch, so pages stay small.
+    let (page, per_page) = page_params(&query_params, 10, 30);
 
-    github_request(
-        &token,
-        Method::Delete,
-        &format!("https://api.github.com/gists/{}", payload.gist_id),
-        None,
+    let row_filter = acl_service::load_row_filter(
+        &ctx.env,
+        principal,
+        gist_id,
+        collection_name,
+        AclAction::Read,
     )
     .await?;
+    let history = history_service::object_history(
+        token,
+        gist_id,
+        collection_name,
+        object_id,
+        page,
+        per_page,
+        row_filter.as_ref(),
+    )
+    .await?;
+    api_response(200, Some(history), "Object history", "")
+}
+
+pub async fn rollback_database(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let gist_id = ctx
+        .param("gistId")
+        .ok_or_else(|| GistDbError::BadRequest("Missing gist ID".to_string()))?;
+    let dry_run = req
+        .url()?
+        .query_pairs()
+        .any(|(key, value)| key == "dry_run" && value != "false");
+    let payload: RollbackRequest = parse_body(&mut req).await?;
+
+    let target = Database::load_at(token, gist_id, Some(&payload.version)).await?;
+    let mut db = Database::load(token, gist_id).await?;
+    let changes = db.rollback_to(&target, payload.collections.as_deref())?;
 
+    let mut new_version = Value::Null;
+    let changed = changes.as_object().is_some_and(|c| !c.is_empty());
+    if !dry_run && changed {
+        let gist = db.commit(token).await?;
+        new_version = gist["history"][0]["version"].clone();
+    }
+
+    let message = match (changed, dry_run) {
+        (false, _) => "Nothing to roll back",
+        (true, true) => "Rollback planned",
+        (true, false) => "Database rolled back",
+    };
     api_response(
         200,
-        Some(json!({ "deleted_gist": payload.gist_id })),
-        "Database deleted",
+        Some(json!({
+            "gist_id": gist_id,
+            "restored_from": payload.version,
+            "dry_run": dry_run,
+            "version": new_version,
+            "collections": changes,
+        })),
+        message,
+        "",
+    )
+}
+
+pub async fn export_database(_req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let gist_id = ctx
+        .param("gistId")
+        .ok_or_else(|| GistDbError::BadRequest("Missing gist ID".to_string()))?;
+
+    let bundle = Database::load(token, gist_id).await?.export()?;
+    let filename = format!("{}.gistdb.json", bundle.manifest.name);
+
+    let mut headers = Headers::new();
+    headers.set(
+        "Content-Disposition",
+        &format!("attachment; filename=\"{}\"", filename.replace('"', "")),
+    )?;
+    Ok(Response::from_json(&bundle)?.with_headers(headers))
+}
+
+pub async fn import_database(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let name = req
+        .url()?
+        .query_pairs()
+        .find(|(key, _)| key == "name")
+        .map(|(_, value)| value.into_owned());
+    let bundle: Bundle = parse_body(&mut req).await?;
+
+    let (gist_id, manifest) =
+        database_service::import_database(token, bundle, name.as_deref()).await?;
+    let needs_sweep = manifest
+        .collections
+        .values()
+        .any(|settings| settings.ttl.is_some() || settings.retention_days.is_some());
+    // The gist exists by now; failing here would only make a retry import it
+    // twice.
+    if needs_sweep {
+        if let Err(err) = sweep_service::register(&ctx.env, token, &gist_id).await {
+            console_error!("registering {} for sweeps failed: {}", gist_id, err);
+        }
+    }
+
+    api_response(
+        201,
+        Some(json!({
+            "gist_id": gist_id,
+            "name": manifest.name,
+            "format_version": manifest.format_version,
+            "collections": manifest.collections.keys().collect::<Vec<_>>(),
+        })),
+        "Database imported",
         "",
     )
 }
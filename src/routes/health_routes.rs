@@ -1,7 +1,8 @@
+use crate::models::auth_models::AuthContext;
 use serde_json::json;
 use worker::{Request, Response, Result, RouteContext};
 
-pub async fn health_check(_req: Request, ctx: RouteContext<()>) -> Result<Response> {
+pub async fn health_check(_req: Request, ctx: RouteContext<AuthContext>) -> Result<Response> {
     let environment = ctx
         .var("ENVIRONMENT")
         .map(|env| env.to_string())
@@ -0,0 +1,45 @@
+use crate::models::auth_models::AuthContext;
+use crate::models::error_models::ApiResult;
+use crate::models::manifest_models::IndexDefinition;
+use crate::models::request_models::{CreateIndexRequest, DeleteIndexRequest};
+use crate::services::database_service::Database;
+use crate::utils::api_response::api_response;
+use crate::utils::request::parse_body;
+use serde_json::json;
+use worker::{Request, Response, RouteContext};
+
+pub async fn create_index(mut req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: CreateIndexRequest = parse_body(&mut req).await?;
+
+    let index = IndexDefinition {
+        name: payload
+            .name
+            .unwrap_or_else(|| payload.fields.join("_").replace('.', "_")),
+        collection: payload.collection_name,
+        fields: payload.fields,
+        unique: payload.unique,
+    };
+
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    db.add_index(index.clone())?;
+    db.commit(token).await?;
+
+    api_response(201, Some(serde_json::to_value(index)?), "Index created", "")
+}
+
+pub async fn delete_index(mut req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: DeleteIndexRequest = parse_body(&mut req).await?;
+
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    db.remove_index(&payload.collection_name, &payload.name)?;
+    db.commit(token).await?;
+
+    api_response(
+        200,
+        Some(json!({ "deleted_index": payload.name })),
+        "Index deleted",
+        "",
+    )
+}
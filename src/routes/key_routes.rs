@@ -0,0 +1,64 @@
+use crate::models::auth_models::{ApiKeyRecord, AuthContext, Principal};
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::models::request_models::CreateApiKeyRequest;
+use crate::services::key_service;
+use crate::utils::api_response::api_response;
+use crate::utils::request::parse_body;
+use serde_json::{json, Value};
+use worker::{Request, Response, RouteContext};
+
+fn key_owner(ctx: &RouteContext<AuthContext>) -> ApiResult<&Principal> {
+    let principal = ctx.data.principal()?;
+    if !principal.is_github_token() {
+        return Err(GistDbError::Forbidden(
+            "API keys can only be managed with a GitHub token".to_string(),
+        ));
+    }
+    Ok(principal)
+}
+
+fn key_summary(record: &ApiKeyRecord) -> Value {
+    json!({
+        "key_id": record.id,
+        "name": record.name,
+        "role": record.role,
+        "scopes": record.scopes,
+        "created_at": record.created_at,
+        "revoked_at": record.revoked_at,
+    })
+}
+
+pub async fn create_key(mut req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let owner = key_owner(&ctx)?;
+    let payload: CreateApiKeyRequest = parse_body(&mut req).await?;
+
+    let (api_key, record) =
+        key_service::create_key(&ctx.env, owner, payload.name, payload.role, payload.scopes)
+            .await?;
+
+    let mut data = key_summary(&record);
+    data["api_key"] = json!(api_key);
+    api_response(201, Some(data), "API key created", "")
+}
+
+pub async fn list_keys(_req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let owner = key_owner(&ctx)?;
+    let records = key_service::list_keys(&ctx.env, owner).await?;
+
+    api_response(
+        200,
+        Some(Value::Array(records.iter().map(key_summary).collect())),
+        "API keys",
+        "",
+    )
+}
+
+pub async fn revoke_key(_req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let owner = key_owner(&ctx)?;
+    let key_id = ctx
+        .param("keyId")
+        .ok_or_else(|| GistDbError::BadRequest("Missing key ID".to_string()))?;
+
+    let record = key_service::revoke_key(&ctx.env, owner, key_id).await?;
+    api_response(200, Some(key_summary(&record)), "API key revoked", "")
+}
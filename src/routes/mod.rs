@@ -1,5 +1,11 @@
+pub mod acl_routes;
 pub mod collection_routes;
 pub mod database_routes;
 pub mod health_routes;
+pub mod index_routes;
+pub mod key_routes;
 pub mod object_routes;
+pub mod relation_routes;
 pub mod search_routes;
+pub mod share_routes;
+pub mod trash_routes;
@@ -1,180 +1,227 @@
+use crate::models::auth_models::{AclAction, AuthContext};
+use crate::models::error_models::{ApiResult, GistDbError};
 use crate::models::request_models::{
-    CreateObjectRequest, DeleteObjectRequest, UpdateObjectRequest,
+    CreateObjectRequest, DeleteManyRequest, DeleteObjectRequest, UpdateManyRequest,
+    UpdateObjectRequest,
 };
-use crate::services::github_service::github_request;
+use crate::services::acl_service;
+use crate::services::database_service::Database;
 use crate::utils::api_response::api_response;
-use serde_json::{json, Map, Value};
+use crate::utils::filter::Filter;
+use crate::utils::request::parse_body;
+use crate::utils::update::Update;
+use serde_json::{json, Value};
 use uuid::Uuid;
-use worker::{Method, Request, Response, Result, RouteContext};
+use worker::{Request, Response, RouteContext};
 
-pub async fn create_object(mut req: Request, _ctx: RouteContext<()>) -> Result<Response> {
-    let token = match req.headers().get("Authorization")? {
-        Some(h) => h.replace("Bearer ", ""),
-        None => return api_response(401, None, "", "Authorization header required"),
-    };
-
-    let payload: CreateObjectRequest = match req.json().await {
-        Ok(p) => p,
-        Err(_) => return api_response(400, None, "", "Invalid request body"),
-    };
+pub async fn create_object(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: CreateObjectRequest = parse_body(&mut req).await?;
 
-    let filename = format!("{}.json", payload.collection_name);
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    let mut data = db.collection(&payload.collection_name)?;
 
-    let gist = github_request(
-        &token,
-        Method::Get,
-        &format!("https://api.github.com/gists/{}", payload.gist_id),
+    let object_id = Uuid::new_v4().to_string();
+    let object = db.write_object(
+        &payload.collection_name,
+        &mut data,
+        &object_id,
+        payload.data,
         None,
-    )
-    .await
-    .map_err(|e| e.to_string())?;
-
-    let file = gist["files"][&filename]
-        .as_object()
-        .ok_or("Collection not found")?;
+    )?;
 
-    let content = file["content"].as_str().unwrap_or("{}");
-    let mut data: Map<String, Value> = serde_json::from_str(content)?;
+    db.put_collection(&payload.collection_name, &data)?;
+    db.commit(token).await?;
 
-    let object_id = Uuid::new_v4().to_string();
-    data.insert(object_id.clone(), payload.data.clone());
-
-    let description = gist["description"].as_str().unwrap_or("GistDB Database");
-    let update_body = json!({
-        "description": description,
-        "files": { filename: { "content": serde_json::to_string(&data)? } }
-    });
-
-    match github_request(
-        &token,
-        Method::Patch,
-        &format!("https://api.github.com/gists/{}", payload.gist_id),
-        Some(update_body),
+    api_response(
+        201,
+        Some(json!({ "object_id": object_id, "data": object })),
+        "Object created",
+        "",
     )
-    .await
-    {
-        Ok(_) => api_response(
-            201,
-            Some(json!({ "object_id": object_id, "data": payload.data })),
-            "Object created",
-            "",
-        ),
-        Err(e) => api_response(500, None, "", &e.to_string()),
-    }
 }
 
-pub async fn update_object(mut req: Request, _ctx: RouteContext<()>) -> Result<Response> {
-    let token = match req.headers().get("Authorization")? {
-        Some(h) => h.replace("Bearer ", ""),
-        None => return api_response(401, None, "", "Authorization header required"),
-    };
-
-    let payload: UpdateObjectRequest = match req.json().await {
-        Ok(p) => p,
-        Err(_) => return api_response(400, None, "", "Invalid request body"),
+pub async fn update_object(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let principal = ctx.data.principal()?;
+    let token = &principal.token;
+    let payload: UpdateObjectRequest = parse_body(&mut req).await?;
+    let update = match (&payload.data, &payload.update) {
+        (Some(_), None) => None,
+        (None, Some(spec)) => Some(Update::parse(spec)?),
+        _ => {
+            return Err(GistDbError::BadRequest(
+                "Provide exactly one of 'data' or 'update'".to_string(),
+            ))
+        }
     };
 
-    let filename = format!("{}.json", payload.collection_name);
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    let mut data = db.collection(&payload.collection_name)?;
 
-    let gist = github_request(
-        &token,
-        Method::Get,
-        &format!("https://api.github.com/gists/{}", payload.gist_id),
-        None,
+    let row_filter = acl_service::load_row_filter(
+        &ctx.env,
+        principal,
+        &payload.gist_id,
+        &payload.collection_name,
+        AclAction::Update,
     )
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?;
+    let existing = data
+        .get(&payload.object_id)
+        .filter(|object| row_filter.as_ref().is_none_or(|f| f.matches(object)))
+        .cloned()
+        .ok_or_else(|| GistDbError::NotFound("Object not found".to_string()))?;
+    let object = match update {
+        Some(update) => {
+            let mut object = existing;
+            update.apply(&mut object)?;
+            object
+        }
+        None => payload.data.unwrap_or_default(),
+    };
 
-    let file = gist["files"][&filename]
-        .as_object()
-        .ok_or("Collection not found")?;
+    let object = db.write_object(
+        &payload.collection_name,
+        &mut data,
+        &payload.object_id,
+        object,
+        row_filter.as_ref(),
+    )?;
+    db.put_collection(&payload.collection_name, &data)?;
+    db.commit(token).await?;
 
-    let content = file["content"].as_str().unwrap_or("{}");
-    let mut data: Map<String, Value> = serde_json::from_str(content)?;
+    api_response(
+        200,
+        Some(json!({ "updated": payload.object_id, "data": object })),
+        "Object updated",
+        "",
+    )
+}
 
-    if !data.contains_key(&payload.object_id) {
-        return api_response(404, None, "", "Object not found");
+pub async fn delete_object(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let principal = ctx.data.principal()?;
+    let token = &principal.token;
+    let payload: DeleteObjectRequest = parse_body(&mut req).await?;
+
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    let data = db.collection(&payload.collection_name)?;
+
+    let row_filter = acl_service::load_row_filter(
+        &ctx.env,
+        principal,
+        &payload.gist_id,
+        &payload.collection_name,
+        AclAction::Delete,
+    )
+    .await?;
+    let visible = data
+        .get(&payload.object_id)
+        .is_some_and(|object| row_filter.is_none_or(|f| f.matches(object)));
+    if !visible {
+        return Err(GistDbError::NotFound("Object not found".to_string()));
     }
+    let changes = db.delete_objects(
+        &payload.collection_name,
+        std::slice::from_ref(&payload.object_id),
+    )?;
+    db.commit(token).await?;
 
-    data.insert(payload.object_id.to_string(), payload.data);
-
-    let description = gist["description"].as_str().unwrap_or("GistDB Database");
-    let body = json!({
-        "description": description,
-        "files": { filename: { "content": serde_json::to_string(&data)? } }
-    });
-
-    match github_request(
-        &token,
-        Method::Patch,
-        &format!("https://api.github.com/gists/{}", payload.gist_id),
-        Some(body),
+    api_response(
+        200,
+        Some(json!({ "deleted": payload.object_id, "changes": changes })),
+        "Object deleted",
+        "",
     )
-    .await
-    {
-        Ok(_) => api_response(
-            200,
-            Some(json!({ "updated": payload.object_id })),
-            "Object updated",
-            "",
-        ),
-        Err(e) => api_response(500, None, "", &e.to_string()),
-    }
 }
 
-pub async fn delete_object(mut req: Request, _ctx: RouteContext<()>) -> Result<Response> {
-    let token = match req.headers().get("Authorization")? {
-        Some(h) => h.replace("Bearer ", ""),
-        None => return api_response(401, None, "", "Authorization header required"),
-    };
-
-    let payload: DeleteObjectRequest = match req.json().await {
-        Ok(p) => p,
-        Err(_) => return api_response(400, None, "", "Invalid request body"),
-    };
-
-    let filename = format!("{}.json", payload.collection_name);
-
-    let gist = github_request(
-        &token,
-        Method::Get,
-        &format!("https://api.github.com/gists/{}", payload.gist_id),
-        None,
+pub async fn update_many(mut req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let principal = ctx.data.principal()?;
+    let token = &principal.token;
+    let payload: UpdateManyRequest = parse_body(&mut req).await?;
+    let filter = Filter::parse(&payload.filter)?;
+    let update = Update::parse(&payload.update)?;
+
+    let row_filter = acl_service::load_row_filter(
+        &ctx.env,
+        principal,
+        &payload.gist_id,
+        &payload.collection_name,
+        AclAction::Update,
     )
     .await?;
-
-    let file = match gist["files"].get(&filename) {
-        Some(f) => f,
-        None => return api_response(404, None, "", "Collection not found"),
-    };
-
-    let content = file["content"].as_str().unwrap_or("{}");
-    let mut data: Map<String, Value> = serde_json::from_str(content)?;
-
-    if !data.contains_key(&payload.object_id) {
-        return api_response(404, None, "", "Object not found");
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    let mut data = db.collection(&payload.collection_name)?;
+
+    let matched: Vec<(String, Value)> = data
+        .iter()
+        .filter(|(_, object)| {
+            filter.matches(object) && row_filter.as_ref().is_none_or(|f| f.matches(object))
+        })
+        .map(|(id, object)| (id.clone(), object.clone()))
+        .collect();
+    let mut writer = db.object_writer(&payload.collection_name, &data)?;
+    for (object_id, mut object) in matched.iter().cloned() {
+        update.apply(&mut object)?;
+        writer.write(&mut data, &object_id, object, row_filter.as_ref())?;
     }
 
-    data.remove(&payload.object_id);
+    if !matched.is_empty() {
+        db.put_collection(&payload.collection_name, &data)?;
+        db.commit(token).await?;
+    }
 
-    let description = gist["description"].as_str().unwrap_or("GistDB Database");
-    let body = json!({
-        "description": description,
-        "files": { filename: { "content": serde_json::to_string(&data)? } }
-    });
+    let object_ids: Vec<&String> = matched.iter().map(|(id, _)| id).collect();
+    api_response(
+        200,
+        Some(json!({ "matched": object_ids.len(), "object_ids": object_ids })),
+        "Objects updated",
+        "",
+    )
+}
 
-    github_request(
-        &token,
-        Method::Patch,
-        &format!("https://api.github.com/gists/{}", payload.gist_id),
-        Some(body),
+pub async fn delete_many(mut req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let principal = ctx.data.principal()?;
+    let token = &principal.token;
+    let payload: DeleteManyRequest = parse_body(&mut req).await?;
+    let filter = Filter::parse(&payload.filter)?;
+
+    let row_filter = acl_service::load_row_filter(
+        &ctx.env,
+        principal,
+        &payload.gist_id,
+        &payload.collection_name,
+        AclAction::Delete,
     )
     .await?;
+    let mut db = Database::load(token, &payload.gist_id).await?;
+
+    let object_ids: Vec<String> = db
+        .collection(&payload.collection_name)?
+        .into_iter()
+        .filter(|(_, object)| {
+            filter.matches(object) && row_filter.as_ref().is_none_or(|f| f.matches(object))
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    let changes = db.delete_objects(&payload.collection_name, &object_ids)?;
+    if !object_ids.is_empty() {
+        db.commit(token).await?;
+    }
 
     api_response(
         200,
-        Some(json!({ "deleted": payload.object_id })),
-        "Object deleted",
+        Some(json!({ "deleted": object_ids.len(), "object_ids": object_ids, "changes": changes })),
+        "Objects deleted",
         "",
     )
 }
@@ -0,0 +1,61 @@
+use crate::models::auth_models::AuthContext;
+use crate::models::error_models::ApiResult;
+use crate::models::manifest_models::{OnDelete, Relation};
+use crate::models::request_models::{CreateRelationRequest, DeleteRelationRequest};
+use crate::services::database_service::Database;
+use crate::utils::api_response::api_response;
+use crate::utils::request::parse_body;
+use serde_json::json;
+use worker::{Request, Response, RouteContext};
+
+pub async fn create_relation(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: CreateRelationRequest = parse_body(&mut req).await?;
+
+    let relation = Relation {
+        name: payload.name.unwrap_or_else(|| {
+            format!(
+                "{}_{}",
+                payload.collection_name,
+                payload.field.replace('.', "_")
+            )
+        }),
+        collection: payload.collection_name,
+        field: payload.field,
+        references: payload.references,
+        on_delete: payload.on_delete.unwrap_or(OnDelete::Restrict),
+    };
+
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    db.add_relation(relation.clone())?;
+    db.commit(token).await?;
+
+    api_response(
+        201,
+        Some(serde_json::to_value(relation)?),
+        "Relation created",
+        "",
+    )
+}
+
+pub async fn delete_relation(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: DeleteRelationRequest = parse_body(&mut req).await?;
+
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    db.remove_relation(&payload.name)?;
+    db.commit(token).await?;
+
+    api_response(
+        200,
+        Some(json!({ "deleted_relation": payload.name })),
+        "Relation deleted",
+        "",
+    )
+}
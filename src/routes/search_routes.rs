@@ -1,29 +1,81 @@
+use crate::models::auth_models::{AclAction, AuthContext};
+use crate::models::error_models::ApiResult;
 use crate::models::request_models::SearchRequest;
-use crate::services::github_service;
+use crate::services::database_service::Database;
+use crate::services::{acl_service, lookup_service};
 use crate::utils::api_response::api_response;
+use crate::utils::crypto::sha256_hex;
+use crate::utils::filter::Filter;
+use crate::utils::request::parse_body;
 use crate::utils::{cache, search};
 use serde_json::{json, Value};
 use worker::Cache;
 use worker::*;
 
-pub async fn search_objects(mut req: Request, _ctx: RouteContext<()>) -> Result<Response> {
-    let token = search::get_auth_token(&req)?;
-    let payload: SearchRequest = search::parse_body(&mut req).await?;
+pub async fn search_objects(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let principal = ctx.data.principal()?;
+    let token = &principal.token;
+    let payload: SearchRequest = parse_body(&mut req).await?;
 
-    let filename = format!("{}.json", payload.collection_name);
+    let user_filter = payload.filter.as_ref().map(Filter::parse).transpose()?;
+    let row_filter = acl_service::load_row_filter(
+        &ctx.env,
+        principal,
+        &payload.gist_id,
+        &payload.collection_name,
+        AclAction::Search,
+    )
+    .await?;
+    let filter = Filter::and(user_filter.into_iter().chain(row_filter));
+    let lookups = lookup_service::plan(
+        &ctx.env,
+        principal,
+        &payload.gist_id,
+        payload.lookup.clone(),
+    )
+    .await?;
+
+    let db = Database::load_at(token, &payload.gist_id, payload.at_revision.as_deref()).await?;
+    // Results change with every revision, and also when an object expires.
     let cache_key = format!(
         "https://gistdb.com/search/{}/{}",
-        payload.gist_id, payload.query
+        payload.gist_id,
+        sha256_hex(&format!(
+            "{:?}|{:?}|{}|{:?}|{:?}|{:?}|{:?}",
+            db.version(),
+            db.next_expiry(&payload.collection_name)?,
+            payload.collection_name,
+            payload.query,
+            payload.field,
+            filter,
+            lookups
+        ))
     );
 
     let cache = Cache::default();
 
-    let results = cache::cached_search(&cache, &cache_key, async {
-        let content = github_service::get_gist_file(&token, &payload.gist_id, &filename).await?;
-        let data: Value = serde_json::from_str(&content)
-            .map_err(|e| worker::Error::RustError(format!("Failed to parse JSON: {}", e)))?;
-        let search_results = search::search_json(&data, &payload.query, payload.field.as_deref())?;
-        Ok(json!(search_results))
+    let results = cache::cached_json(&cache, &cache_key, async {
+        let data = Value::Object(db.collection(&payload.collection_name)?);
+        let search_results = search::search_json(
+            &data,
+            payload.query.as_deref(),
+            payload.field.as_deref(),
+            filter.as_ref(),
+        )?;
+        if lookups.is_empty() {
+            return Ok(json!(search_results));
+        }
+
+        let mut objects: Vec<Value> = search_results.iter().map(|id| data[id].clone()).collect();
+        lookup_service::resolve(&db, &lookups, objects.iter_mut())?;
+        Ok(search_results
+            .into_iter()
+            .zip(objects)
+            .map(|(object_id, data)| json!({ "object_id": object_id, "data": data }))
+            .collect())
     })
     .await?;
 
@@ -0,0 +1,47 @@
+use crate::models::auth_models::AuthContext;
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::models::request_models::CreateShareLinkRequest;
+use crate::services::acl_service;
+use crate::services::share_service::{self, DEFAULT_SHARE_TTL};
+use crate::utils::api_response::api_response;
+use crate::utils::request::parse_body;
+use chrono::DateTime;
+use serde_json::json;
+use worker::{Request, Response, RouteContext};
+
+pub async fn create_share_link(
+    mut req: Request,
+    ctx: RouteContext<AuthContext>,
+) -> ApiResult<Response> {
+    let principal = ctx.data.principal()?;
+    let payload: CreateShareLinkRequest = parse_body(&mut req).await?;
+    if principal.enforces_acl() {
+        acl_service::check_shareable(
+            &acl_service::load_acl(&ctx.env, &payload.gist_id).await?,
+            principal,
+            payload.collection_name.as_deref(),
+        )?;
+    }
+
+    let (share_token, expires_at) = share_service::create_share_token(
+        &ctx.env,
+        principal,
+        &payload.gist_id,
+        payload.collection_name.as_deref(),
+        payload.expires_in.unwrap_or(DEFAULT_SHARE_TTL),
+    )?;
+    let expires_at = DateTime::from_timestamp(expires_at, 0)
+        .ok_or_else(|| GistDbError::Internal("Invalid expiry timestamp".to_string()))?;
+
+    api_response(
+        201,
+        Some(json!({
+            "share_token": share_token,
+            "gist_id": payload.gist_id,
+            "collection_name": payload.collection_name,
+            "expires_at": expires_at.to_rfc3339(),
+        })),
+        "Share link created",
+        "",
+    )
+}
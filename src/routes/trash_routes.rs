@@ -0,0 +1,78 @@
+use crate::models::auth_models::{AclAction, AuthContext};
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::models::request_models::{PurgeTrashRequest, RestoreRequest};
+use crate::services::acl_service;
+use crate::services::database_service::Database;
+use crate::utils::api_response::api_response;
+use crate::utils::request::parse_body;
+use serde_json::json;
+use worker::{Request, Response, RouteContext};
+
+pub async fn list_trash(_req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let gist_id = ctx
+        .param("gistId")
+        .ok_or_else(|| GistDbError::BadRequest("Missing gist ID".to_string()))?;
+
+    let db = Database::load(token, gist_id).await?;
+    api_response(200, Some(serde_json::to_value(&db.trash)?), "Trash", "")
+}
+
+pub async fn restore(mut req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let principal = ctx.data.principal()?;
+    let token = &principal.token;
+    let payload: RestoreRequest = parse_body(&mut req).await?;
+
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    let data = match &payload.object_id {
+        Some(object_id) => {
+            let row_filter = |action| {
+                acl_service::load_row_filter(
+                    &ctx.env,
+                    principal,
+                    &payload.gist_id,
+                    &payload.collection_name,
+                    action,
+                )
+            };
+            let visible = row_filter(AclAction::Read).await?;
+            let policy = row_filter(AclAction::Create).await?;
+            let trashed = db
+                .trash
+                .objects
+                .get(&payload.collection_name)
+                .and_then(|objects| objects.get(object_id));
+            if trashed.is_some_and(|t| visible.as_ref().is_some_and(|f| !f.matches(&t.data))) {
+                return Err(GistDbError::NotFound(format!(
+                    "Object '{}' is not in the trash of '{}'",
+                    object_id, payload.collection_name
+                )));
+            }
+            json!({
+                "object_id": object_id,
+                "data": db.restore_object(&payload.collection_name, object_id, policy.as_ref())?,
+            })
+        }
+        None => json!({
+            "collection_name": payload.collection_name,
+            "restored_objects": db.restore_collection(&payload.collection_name)?,
+        }),
+    };
+    db.commit(token).await?;
+
+    api_response(200, Some(data), "Restored from trash", "")
+}
+
+pub async fn purge(mut req: Request, ctx: RouteContext<AuthContext>) -> ApiResult<Response> {
+    let token = &ctx.data.principal()?.token;
+    let payload: PurgeTrashRequest = parse_body(&mut req).await?;
+
+    let mut db = Database::load(token, &payload.gist_id).await?;
+    let purged = db.purge_trash(
+        payload.collection_name.as_deref(),
+        payload.object_id.as_deref(),
+    )?;
+    db.commit(token).await?;
+
+    api_response(200, Some(json!({ "purged": purged })), "Trash purged", "")
+}
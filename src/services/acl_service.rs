@@ -0,0 +1,418 @@
+use crate::models::auth_models::{
+    AclAction, AclRule, DatabaseAcl, Operation, Permission, Principal,
+};
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::services::github_service;
+use crate::services::key_service::keys_store;
+use crate::utils::filter::{bind_variables, Filter};
+use serde_json::{json, Map, Value};
+use worker::{Env, Method, Request};
+
+fn acl_key(gist_id: &str) -> String {
+    format!("acl:{}", gist_id)
+}
+
+pub async fn load_acl(env: &Env, gist_id: &str) -> ApiResult<DatabaseAcl> {
+    Ok(keys_store(env)?
+        .get(&acl_key(gist_id))
+        .json()
+        .await?
+        .unwrap_or_default())
+}
+
+pub async fn save_acl(env: &Env, gist_id: &str, acl: &DatabaseAcl) -> ApiResult<()> {
+    for rule in acl.collections.values() {
+        for policy in &rule.policies {
+            Filter::parse(&policy.filter)?;
+        }
+    }
+
+    keys_store(env)?
+        .put(&acl_key(gist_id), acl)?
+        .execute()
+        .await?;
+    Ok(())
+}
+
+/// Rules are stored outside the gist, so a token that can merely read it
+/// (any token, for a public gist) must not be able to see or change them.
+pub async fn require_owner(principal: &Principal, gist_id: &str) -> ApiResult<()> {
+    let gist = github_service::get_gist(&principal.token, gist_id).await?;
+    let login = match &principal.login {
+        Some(login) => login.clone(),
+        None => github_service::get_user(&principal.token).await?["login"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| {
+                GistDbError::Unauthorized("Could not identify the GitHub user".to_string())
+            })?,
+    };
+
+    if gist["owner"]["login"].as_str() == Some(login.as_str()) {
+        Ok(())
+    } else {
+        Err(GistDbError::Forbidden(
+            "Only the owner of the database may manage its access rules".to_string(),
+        ))
+    }
+}
+
+async fn request_body(req: &Request) -> ApiResult<Value> {
+    req.clone()?
+        .json()
+        .await
+        .map_err(|e| GistDbError::BadRequest(format!("Invalid request body: {}", e)))
+}
+
+fn body_field(body: &Value, field: &str) -> ApiResult<String> {
+    body[field]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| GistDbError::BadRequest(format!("Missing '{}' in request body", field)))
+}
+
+fn operation(
+    database: String,
+    collection: Option<String>,
+    permission: Permission,
+    action: Option<AclAction>,
+) -> Option<Operation> {
+    Some(Operation {
+        database,
+        collection,
+        permission,
+        action,
+    })
+}
+
+pub async fn resolve_operation(req: &Request) -> ApiResult<Option<Operation>> {
+    let path = req.path();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    let op = match (req.method(), segments.as_slice()) {
+        (Method::Post, ["api", "databases"] | ["api", "databases", "import"]) => {
+            operation("*".to_string(), None, Permission::Admin, None)
+        }
+        (Method::Delete, ["api", "databases"]) => {
+            let body = request_body(req).await?;
+            operation(body_field(&body, "gist_id")?, None, Permission::Admin, None)
+        }
+        (Method::Get | Method::Put, ["api", "databases", gist_id, "acl"])
+        | (Method::Get, ["api", "databases", gist_id, "trash" | "export"])
+        | (Method::Post, ["api", "databases", gist_id, "migrate" | "sweep" | "rollback"]) => {
+            operation(gist_id.to_string(), None, Permission::Admin, None)
+        }
+        (Method::Get, ["api", "databases", gist_id, "revisions"]) => {
+            operation(gist_id.to_string(), None, Permission::Read, None)
+        }
+        (Method::Get, ["api", "databases", gist_id, "history"]) => {
+            let collection = req
+                .url()?
+                .query_pairs()
+                .find(|(key, _)| key == "collection_name")
+                .map(|(_, value)| value.into_owned());
+            operation(
+                gist_id.to_string(),
+                collection,
+                Permission::Read,
+                Some(AclAction::Read),
+            )
+        }
+        (Method::Get, ["api", "databases", gist_id, "collections", collection, "export"]) => {
+            operation(
+                gist_id.to_string(),
+                Some(collection.to_string()),
+                Permission::Read,
+                Some(AclAction::Read),
+            )
+        }
+        (Method::Post, ["api", "databases", gist_id, "collections", collection, "import"]) => {
+            operation(
+                gist_id.to_string(),
+                Some(collection.to_string()),
+                Permission::Write,
+                Some(AclAction::Create),
+            )
+        }
+        (Method::Post, ["api", "collections"]) => {
+            let body = request_body(req).await?;
+            operation(
+                body_field(&body, "gist_id")?,
+                Some(body_field(&body, "name")?),
+                Permission::Write,
+                Some(AclAction::Create),
+            )
+        }
+        (Method::Put, ["api", "collections", "schema" | "fields" | "trash" | "ttl"]) => {
+            let body = request_body(req).await?;
+            operation(
+                body_field(&body, "gist_id")?,
+                Some(body_field(&body, "collection_name")?),
+                Permission::Admin,
+                None,
+            )
+        }
+        (Method::Post | Method::Delete, ["api", "indexes"]) => {
+            let body = request_body(req).await?;
+            operation(
+                body_field(&body, "gist_id")?,
+                Some(body_field(&body, "collection_name")?),
+                Permission::Admin,
+                None,
+            )
+        }
+        (Method::Post | Method::Delete, ["api", "relations"]) => {
+            let body = request_body(req).await?;
+            operation(body_field(&body, "gist_id")?, None, Permission::Admin, None)
+        }
+        (Method::Post, ["api", "trash", "restore"]) => {
+            let body = request_body(req).await?;
+            let collection = body_field(&body, "collection_name")?;
+            // A whole collection comes back without row checks.
+            if body["object_id"].is_null() {
+                operation(
+                    body_field(&body, "gist_id")?,
+                    Some(collection),
+                    Permission::Admin,
+                    None,
+                )
+            } else {
+                operation(
+                    body_field(&body, "gist_id")?,
+                    Some(collection),
+                    Permission::Write,
+                    Some(AclAction::Create),
+                )
+            }
+        }
+        (Method::Delete, ["api", "trash"]) => {
+            let body = request_body(req).await?;
+            operation(
+                body_field(&body, "gist_id")?,
+                body["collection_name"].as_str().map(str::to_string),
+                Permission::Admin,
+                None,
+            )
+        }
+        (Method::Delete, ["api", "collections"]) => {
+            let body = request_body(req).await?;
+            operation(
+                body_field(&body, "gist_id")?,
+                Some(body_field(&body, "collection_name")?),
+                Permission::Admin,
+                Some(AclAction::Delete),
+            )
+        }
+        (method @ (Method::Post | Method::Put | Method::Delete), ["api", "objects"]) => {
+            let body = request_body(req).await?;
+            let action = match method {
+                Method::Post => AclAction::Create,
+                Method::Put => AclAction::Update,
+                _ => AclAction::Delete,
+            };
+            operation(
+                body_field(&body, "gist_id")?,
+                Some(body_field(&body, "collection_name")?),
+                Permission::Write,
+                Some(action),
+            )
+        }
+        (Method::Post, ["api", "objects", bulk @ ("update_many" | "delete_many")]) => {
+            let body = request_body(req).await?;
+            let action = if *bulk == "update_many" {
+                AclAction::Update
+            } else {
+                AclAction::Delete
+            };
+            operation(
+                body_field(&body, "gist_id")?,
+                Some(body_field(&body, "collection_name")?),
+                Permission::Write,
+                Some(action),
+            )
+        }
+        (Method::Post, ["api", "share"]) => {
+            let body = request_body(req).await?;
+            operation(
+                body_field(&body, "gist_id")?,
+                body["collection_name"].as_str().map(str::to_string),
+                Permission::Admin,
+                None,
+            )
+        }
+        (Method::Post, ["api", "search"]) => {
+            let body = request_body(req).await?;
+            operation(
+                body_field(&body, "gist_id")?,
+                Some(body_field(&body, "collection_name")?),
+                Permission::Read,
+                Some(AclAction::Search),
+            )
+        }
+        (Method::Get, ["api", "databases" | "keys"]) => None,
+        (Method::Get, ["api", gist_id]) => {
+            let collection = req
+                .url()?
+                .query_pairs()
+                .find(|(key, _)| key == "collection_name")
+                .map(|(_, value)| value.into_owned());
+            operation(
+                gist_id.to_string(),
+                collection,
+                Permission::Read,
+                Some(AclAction::Read),
+            )
+        }
+        _ => None,
+    };
+    Ok(op)
+}
+
+pub async fn authorize(env: &Env, principal: &Principal, op: &Operation) -> ApiResult<()> {
+    principal.authorize(&op.database, op.collection.as_deref(), op.permission)?;
+
+    let Some(action) = op.action else {
+        return Ok(());
+    };
+    if !principal.enforces_acl() {
+        return Ok(());
+    }
+
+    let acl = load_acl(env, &op.database).await?;
+    if acl.allows(op.collection.as_deref(), action, principal.role()) {
+        Ok(())
+    } else {
+        Err(GistDbError::Forbidden(format!(
+            "Role '{}' may not {:?} in this collection",
+            principal.role().unwrap_or("none"),
+            action
+        )))
+    }
+}
+
+/// Share links read with the issuer's token and no rules of their own, so a
+/// key may only share what its role can already read in full.
+pub fn check_shareable(
+    acl: &DatabaseAcl,
+    principal: &Principal,
+    collection: Option<&str>,
+) -> ApiResult<()> {
+    if !principal.enforces_acl() {
+        return Ok(());
+    }
+    let rules: Vec<&AclRule> = match collection {
+        Some(name) => acl.rule(name).into_iter().collect(),
+        None => acl.collections.values().collect(),
+    };
+    let role = principal.role();
+    let restricted = rules.iter().any(|rule| {
+        [AclAction::Read, AclAction::Search]
+            .into_iter()
+            .any(|action| {
+                !rule.allows(action, role)
+                    || rule.policies.iter().any(|p| p.applies_to(action, role))
+            })
+    });
+
+    if restricted {
+        Err(GistDbError::Forbidden(
+            "Access rules limit what this API key can read, so it cannot share this resource"
+                .to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn principal_variables(principal: &Principal) -> Map<String, Value> {
+    let mut variables = Map::new();
+    variables.insert("principal.id".to_string(), json!(principal.id()));
+    variables.insert("principal.role".to_string(), json!(principal.role()));
+    variables.insert("principal.login".to_string(), json!(principal.login));
+    variables
+}
+
+pub fn row_filter(
+    acl: &DatabaseAcl,
+    principal: &Principal,
+    collection: &str,
+    action: AclAction,
+) -> ApiResult<Option<Filter>> {
+    if !principal.enforces_acl() {
+        return Ok(None);
+    }
+    let Some(rule) = acl.rule(collection) else {
+        return Ok(None);
+    };
+
+    let variables = principal_variables(principal);
+    let filters = rule
+        .policies
+        .iter()
+        .filter(|policy| policy.applies_to(action, principal.role()))
+        .map(|policy| Filter::parse(&bind_variables(&policy.filter, &variables)))
+        .collect::<ApiResult<Vec<_>>>()?;
+    Ok(Filter::and(filters))
+}
+
+pub async fn load_row_filter(
+    env: &Env,
+    principal: &Principal,
+    gist_id: &str,
+    collection: &str,
+    action: AclAction,
+) -> ApiResult<Option<Filter>> {
+    if !principal.enforces_acl() {
+        return Ok(None);
+    }
+    row_filter(
+        &load_acl(env, gist_id).await?,
+        principal,
+        collection,
+        action,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::auth_models::PrincipalKind;
+
+    fn key(role: &str) -> Principal {
+        Principal {
+            kind: PrincipalKind::ApiKey {
+                key_id: "key-1".to_string(),
+                role: Some(role.to_string()),
+                scopes: Vec::new(),
+            },
+            ..Principal::github("token".to_string())
+        }
+    }
+
+    fn acl(rules: Value) -> DatabaseAcl {
+        serde_json::from_value(json!({ "collections": rules })).unwrap()
+    }
+
+    #[test]
+    fn keys_cannot_share_collections_with_row_policies() {
+        let acl = acl(json!({
+            "notes": { "policies": [{ "filter": { "owner": "$principal.id" } }] },
+            "posts": { "read": ["*"] }
+        }));
+        assert!(check_shareable(&acl, &key("viewer"), Some("notes")).is_err());
+        assert!(check_shareable(&acl, &key("viewer"), Some("posts")).is_ok());
+        assert!(check_shareable(&acl, &key("viewer"), None).is_err());
+        assert!(check_shareable(&acl, &Principal::github("token".to_string()), None).is_ok());
+    }
+
+    #[test]
+    fn keys_cannot_share_what_their_role_cannot_read() {
+        let acl = acl(json!({
+            "*": { "read": ["admin"] },
+            "posts": { "search": ["admin"] }
+        }));
+        assert!(check_shareable(&acl, &key("viewer"), Some("notes")).is_err());
+        assert!(check_shareable(&acl, &key("viewer"), Some("posts")).is_err());
+        assert!(check_shareable(&acl, &key("admin"), None).is_ok());
+    }
+}
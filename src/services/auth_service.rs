@@ -0,0 +1,141 @@
+use crate::models::auth_models::{AuthContext, Principal};
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::services::acl_service;
+use crate::services::github_service::{github_request_with_headers, GITHUB_API};
+use crate::services::key_service::{self, API_KEY_PREFIX};
+use crate::services::share_service::{self, SHARE_TOKEN_PREFIX};
+use crate::utils::crypto::sha256_hex;
+use worker::{Cache, Env, Headers, Method, Request, Response};
+
+const AUTH_CACHE_TTL: u32 = 300;
+const GITHUB_TOKEN_PREFIXES: [&str; 6] = ["ghp_", "gho_", "ghu_", "ghs_", "ghr_", "github_pat_"];
+
+fn share_token_param(req: &Request) -> ApiResult<Option<String>> {
+    Ok(req
+        .url()?
+        .query_pairs()
+        .find(|(key, value)| key == "token" && value.starts_with(SHARE_TOKEN_PREFIX))
+        .map(|(_, value)| value.into_owned()))
+}
+
+pub fn bearer_token(req: &Request) -> ApiResult<String> {
+    let Some(header) = req.headers().get("Authorization")? else {
+        return share_token_param(req)?
+            .ok_or_else(|| GistDbError::Unauthorized("Authorization header required".to_string()));
+    };
+
+    let token = header
+        .strip_prefix("Bearer ")
+        .or_else(|| header.strip_prefix("bearer "))
+        .map(str::trim)
+        .ok_or_else(|| {
+            GistDbError::Unauthorized("Authorization header must use the Bearer scheme".to_string())
+        })?;
+
+    if token.is_empty() {
+        return Err(GistDbError::Unauthorized(
+            "Bearer token is empty".to_string(),
+        ));
+    }
+    Ok(token.to_string())
+}
+
+pub fn is_github_token(token: &str) -> bool {
+    let valid_chars =
+        |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    GITHUB_TOKEN_PREFIXES
+        .iter()
+        .any(|prefix| token.strip_prefix(prefix).is_some_and(valid_chars))
+        || (token.len() == 40 && token.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn verification_enabled(env: &Env) -> bool {
+    env.var("VERIFY_GITHUB_TOKENS")
+        .map(|v| v.to_string() == "true")
+        .unwrap_or(false)
+}
+
+fn parse_scopes(headers: &Headers) -> Option<Vec<String>> {
+    headers.get("x-oauth-scopes").ok().flatten().map(|scopes| {
+        scopes
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    })
+}
+
+async fn verify_github_token(token: &str) -> ApiResult<Principal> {
+    let cache = Cache::default();
+    let cache_key = format!("https://gistdb.com/auth/{}", sha256_hex(token));
+    if let Some(mut cached) = cache.get(&cache_key, true).await? {
+        let principal: Principal = cached.json().await?;
+        return Ok(Principal {
+            token: token.to_string(),
+            ..principal
+        });
+    }
+
+    let (user, headers) =
+        github_request_with_headers(token, Method::Get, &format!("{}/user", GITHUB_API), None)
+            .await?;
+
+    let principal = Principal {
+        login: user["login"].as_str().map(str::to_string),
+        scopes: parse_scopes(&headers),
+        ..Principal::github(token.to_string())
+    };
+
+    if let Some(scopes) = &principal.scopes {
+        if !scopes.iter().any(|s| s == "gist") {
+            return Err(GistDbError::Forbidden(
+                "GitHub token is missing the 'gist' scope".to_string(),
+            ));
+        }
+    }
+
+    let mut cached = Response::from_json(&principal)?;
+    cached
+        .headers_mut()
+        .set("Cache-Control", &format!("max-age={}", AUTH_CACHE_TTL))?;
+    cache.put(&cache_key, cached).await?;
+
+    Ok(principal)
+}
+
+pub async fn authenticate(req: &Request, env: &Env) -> ApiResult<Principal> {
+    let token = bearer_token(req)?;
+    if token.starts_with(API_KEY_PREFIX) {
+        return key_service::authenticate_key(env, &token).await;
+    }
+    if token.starts_with(SHARE_TOKEN_PREFIX) {
+        return share_service::authenticate_share(env, &token).await;
+    }
+    if !is_github_token(&token) {
+        return Err(GistDbError::Unauthorized(
+            "Bearer token is not a valid GitHub token".to_string(),
+        ));
+    }
+
+    if verification_enabled(env) {
+        verify_github_token(&token).await
+    } else {
+        Ok(Principal::github(token))
+    }
+}
+
+pub async fn authorize_request(req: &Request, env: &Env) -> ApiResult<AuthContext> {
+    if !req.path().starts_with("/api/") {
+        return Ok(AuthContext::default());
+    }
+
+    let principal = authenticate(req, env).await?;
+    if let Some(op) = acl_service::resolve_operation(req).await? {
+        acl_service::authorize(env, &principal, &op).await?;
+    }
+
+    Ok(AuthContext {
+        principal: Some(principal),
+    })
+}
@@ -0,0 +1,1786 @@
+use crate::models::bundle_models::{Bundle, BUNDLE_FORMAT, BUNDLE_VERSION};
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::models::manifest_models::{
+    expiry_of, CollectionSettings, ComputedField, IndexDefinition, Manifest, OnDelete, Relation,
+    TtlSettings, FORMAT_VERSION, MANIFEST_FILE,
+};
+use crate::models::trash_models::{
+    is_expired, Trash, TrashedCollection, TrashedObject, TRASH_FILE,
+};
+use crate::services::github_service::{
+    get_gist, get_gist_revision, github_request, list_gist_commits, patch_gist, GITHUB_API,
+};
+use crate::services::migration_service;
+use crate::utils::diff::diff;
+use crate::utils::filter::Filter;
+use crate::utils::schema::Schema;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use uuid::Uuid;
+use worker::Method;
+
+pub const DESCRIPTION_PREFIX: &str = "GistDB: ";
+const GISTS_PER_PAGE: usize = 100;
+const MAX_GIST_PAGES: usize = 30;
+
+pub fn database_description(name: &str) -> String {
+    format!("{}{}", DESCRIPTION_PREFIX, name)
+}
+
+pub fn collection_file(name: &str) -> String {
+    format!("{}.json", name)
+}
+
+pub fn validate_collection_name(name: &str) -> ApiResult<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('_')
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(GistDbError::BadRequest(format!(
+            "Invalid collection name '{}': use letters, digits, '_', '-' or '.', and do not start with '_' or '.'",
+            name
+        )))
+    }
+}
+
+pub fn database_name(gist: &Value) -> Option<String> {
+    let description = gist["description"].as_str().unwrap_or_default();
+    if let Some(name) = description.strip_prefix(DESCRIPTION_PREFIX) {
+        return Some(name.to_string());
+    }
+
+    let files = gist["files"].as_object()?;
+    if files.contains_key(MANIFEST_FILE) || files.contains_key(&collection_file(description)) {
+        Some(description.to_string())
+    } else {
+        None
+    }
+}
+
+pub fn database_summary(gist: &Value) -> Option<Value> {
+    let name = database_name(gist)?;
+    let files = gist["files"].as_object()?;
+
+    let collections: Vec<&str> = files
+        .keys()
+        .filter(|filename| !filename.starts_with('_'))
+        .filter_map(|filename| filename.strip_suffix(".json"))
+        .collect();
+    let size: u64 = files
+        .values()
+        .filter_map(|file| file["size"].as_u64())
+        .sum();
+
+    Some(json!({
+        "gist_id": gist["id"],
+        "name": name,
+        "collections": collections,
+        "created_at": gist["created_at"],
+        "updated_at": gist["updated_at"],
+        "size": size,
+    }))
+}
+
+pub async fn list_databases(token: &str) -> ApiResult<Vec<Value>> {
+    let mut databases = Vec::new();
+
+    for page in 1..=MAX_GIST_PAGES {
+        let url = format!(
+            "{}/gists?per_page={}&page={}",
+            GITHUB_API, GISTS_PER_PAGE, page
+        );
+        let gists = github_request(token, Method::Get, &url, None).await?;
+        let gists = gists.as_array().cloned().unwrap_or_default();
+
+        databases.extend(gists.iter().filter_map(database_summary));
+        if gists.len() < GISTS_PER_PAGE {
+            break;
+        }
+    }
+
+    Ok(databases)
+}
+
+pub async fn list_revisions(
+    token: &str,
+    gist_id: &str,
+    page: u32,
+    per_page: u32,
+) -> ApiResult<Vec<Value>> {
+    let commits = list_gist_commits(token, gist_id, page, per_page).await?;
+    Ok(commits
+        .as_array()
+        .map(|commits| {
+            commits
+                .iter()
+                .map(|commit| {
+                    json!({
+                        "version": commit["version"],
+                        "committed_at": commit["committed_at"],
+                        "change_status": commit["change_status"],
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+pub async fn create_database(token: &str, name: &str) -> ApiResult<(String, Manifest)> {
+    let manifest = Manifest::new(name);
+    let body = json!({
+        "description": database_description(name),
+        "public": false,
+        "files": {
+            MANIFEST_FILE: { "content": serde_json::to_string_pretty(&manifest)? }
+        }
+    });
+
+    let gist = github_request(
+        token,
+        Method::Post,
+        &format!("{}/gists", GITHUB_API),
+        Some(body),
+    )
+    .await?;
+    let gist_id = gist["id"]
+        .as_str()
+        .ok_or_else(|| GistDbError::Internal("GitHub did not return a gist id".to_string()))?;
+
+    Ok((gist_id.to_string(), manifest))
+}
+
+pub async fn import_database(
+    token: &str,
+    mut bundle: Bundle,
+    name: Option<&str>,
+) -> ApiResult<(String, Manifest)> {
+    if bundle.format != BUNDLE_FORMAT || bundle.bundle_version > BUNDLE_VERSION {
+        return Err(GistDbError::BadRequest(format!(
+            "Not a supported {} (version {} or older)",
+            BUNDLE_FORMAT, BUNDLE_VERSION
+        )));
+    }
+    let manifest = &mut bundle.manifest;
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(GistDbError::conflict(format!(
+            "Database format version {} is newer than the supported version {}",
+            manifest.format_version, FORMAT_VERSION
+        )));
+    }
+    if let Some(name) = name {
+        manifest.name = name.to_string();
+    }
+    manifest.created_at = chrono::Utc::now().to_rfc3339();
+
+    for (collection, settings) in &manifest.collections {
+        validate_collection_name(collection)?;
+        if let Some(schema) = &settings.schema {
+            Schema::parse(schema)?;
+        }
+    }
+    let referenced = bundle
+        .collections
+        .keys()
+        .chain(manifest.indexes.iter().map(|index| &index.collection))
+        .chain(
+            manifest
+                .relations
+                .iter()
+                .flat_map(|relation| [&relation.collection, &relation.references]),
+        );
+    for collection in referenced {
+        if !manifest.collections.contains_key(collection) {
+            return Err(GistDbError::BadRequest(format!(
+                "Bundle refers to collection '{}' which is not in its manifest",
+                collection
+            )));
+        }
+    }
+
+    let mut files = Map::new();
+    files.insert(
+        MANIFEST_FILE.to_string(),
+        json!({ "content": serde_json::to_string_pretty(manifest)? }),
+    );
+    for collection in manifest.collections.keys() {
+        let objects = checked_objects(
+            manifest,
+            collection,
+            bundle.collections.remove(collection).unwrap_or_default(),
+        )?;
+        files.insert(
+            collection_file(collection),
+            json!({ "content": serde_json::to_string(&objects)? }),
+        );
+    }
+    if !bundle.trash.objects.is_empty() || !bundle.trash.collections.is_empty() {
+        files.insert(
+            TRASH_FILE.to_string(),
+            json!({ "content": serde_json::to_string_pretty(&bundle.trash)? }),
+        );
+    }
+
+    let gist = github_request(
+        token,
+        Method::Post,
+        &format!("{}/gists", GITHUB_API),
+        Some(json!({
+            "description": database_description(&manifest.name),
+            "public": false,
+            "files": files,
+        })),
+    )
+    .await?;
+    let gist_id = gist["id"]
+        .as_str()
+        .ok_or_else(|| GistDbError::Internal("GitHub did not return a gist id".to_string()))?;
+
+    Ok((gist_id.to_string(), bundle.manifest))
+}
+
+/// Runs bundled objects through the same checks as writes, so an edited or
+/// damaged bundle cannot create a database that breaks its own constraints.
+fn checked_objects(
+    manifest: &Manifest,
+    collection: &str,
+    objects: Map<String, Value>,
+) -> ApiResult<Map<String, Value>> {
+    let mut writer = ObjectWriter::new(manifest, collection, &Map::new())?;
+    let mut checked = Map::new();
+    for (id, object) in objects {
+        writer
+            .write(&mut checked, &id, object, None)
+            .map_err(|e| e.within(&format!("Object '{}' in '{}'", id, collection)))?;
+    }
+    Ok(checked)
+}
+
+fn unique_key(index: &IndexDefinition, object: &Value) -> Option<String> {
+    index.key(object).map(|key| Value::Array(key).to_string())
+}
+
+fn unique_violation(
+    index: &IndexDefinition,
+    collection: &str,
+    conflicting_id: &str,
+) -> GistDbError {
+    GistDbError::Conflict {
+        message: format!(
+            "Unique index '{}' on '{}' is violated by object '{}'",
+            index.name, collection, conflicting_id
+        ),
+        details: Some(json!({
+            "index": index.name,
+            "fields": index.fields,
+            "conflicting_id": conflicting_id,
+        })),
+    }
+}
+
+pub struct ObjectWriter<'a> {
+    collection: &'a str,
+    settings: &'a CollectionSettings,
+    schema: Option<Schema>,
+    unique: Vec<(&'a IndexDefinition, HashMap<String, String>)>,
+}
+
+impl<'a> ObjectWriter<'a> {
+    /// Prepares the checks for writing many objects into `data`: the schema is
+    /// parsed once and unique keys are indexed up front.
+    pub fn new(
+        manifest: &'a Manifest,
+        collection: &'a str,
+        data: &Map<String, Value>,
+    ) -> ApiResult<Self> {
+        let settings = manifest.collections.get(collection).ok_or_else(|| {
+            GistDbError::NotFound(format!("Collection '{}' not found", collection))
+        })?;
+        let schema = settings.schema.as_ref().map(Schema::parse).transpose()?;
+        let unique = manifest
+            .indexes
+            .iter()
+            .filter(|i| i.unique && i.collection == collection)
+            .map(|index| {
+                let keys = data
+                    .iter()
+                    .filter_map(|(id, object)| Some((unique_key(index, object)?, id.clone())))
+                    .collect();
+                (index, keys)
+            })
+            .collect();
+        Ok(ObjectWriter {
+            collection,
+            settings,
+            schema,
+            unique,
+        })
+    }
+
+    /// Applies defaults and computed fields, checks the row policy, schema and
+    /// unique indexes, and inserts the object into `data`.
+    pub fn write(
+        &mut self,
+        data: &mut Map<String, Value>,
+        object_id: &str,
+        mut object: Value,
+        policy: Option<&Filter>,
+    ) -> ApiResult<Value> {
+        self.settings.prepare(&mut object);
+        if policy.is_some_and(|f| !f.matches(&object)) {
+            return Err(GistDbError::Forbidden(
+                "Object would violate the collection's row policy".to_string(),
+            ));
+        }
+        if let Some(schema) = &self.schema {
+            let violations = schema.validate(&object);
+            if !violations.is_empty() {
+                return Err(GistDbError::Validation {
+                    message: format!("Object does not match the schema of '{}'", self.collection),
+                    details: Some(json!({ "violations": violations })),
+                });
+            }
+        }
+
+        let mut keys = Vec::with_capacity(self.unique.len());
+        for (index, existing) in &self.unique {
+            let key = unique_key(index, &object);
+            if let Some(conflicting_id) = key
+                .as_ref()
+                .and_then(|key| existing.get(key))
+                .filter(|id| *id != object_id)
+            {
+                return Err(unique_violation(index, self.collection, conflicting_id));
+            }
+            keys.push(key);
+        }
+
+        let previous = data.insert(object_id.to_string(), object.clone());
+        for ((index, existing), key) in self.unique.iter_mut().zip(keys) {
+            if let Some(old_key) = previous.as_ref().and_then(|old| unique_key(index, old)) {
+                if existing.get(&old_key).is_some_and(|id| id == object_id) {
+                    existing.remove(&old_key);
+                }
+            }
+            if let Some(key) = key {
+                existing.insert(key, object_id.to_string());
+            }
+        }
+        Ok(object)
+    }
+}
+
+pub struct Database {
+    pub gist_id: String,
+    pub gist: Value,
+    pub manifest: Manifest,
+    pub trash: Trash,
+    pub revision: Option<String>,
+    files: Map<String, Value>,
+    manifest_changed: bool,
+    trash_changed: bool,
+}
+
+fn legacy_manifest(gist: &Value) -> Manifest {
+    let mut manifest = Manifest::new(&database_name(gist).unwrap_or_default());
+    manifest.format_version = 0;
+    manifest.created_at = gist["created_at"].as_str().unwrap_or_default().to_string();
+    if let Some(files) = gist["files"].as_object() {
+        for filename in files.keys().filter(|f| !f.starts_with('_')) {
+            if let Some(name) = filename.strip_suffix(".json") {
+                manifest
+                    .collections
+                    .insert(name.to_string(), CollectionSettings::default());
+            }
+        }
+    }
+    manifest
+}
+
+impl Database {
+    pub async fn load(token: &str, gist_id: &str) -> ApiResult<Self> {
+        let gist = get_gist(token, gist_id).await?;
+        Self::from_gist(gist_id, gist)
+    }
+
+    pub async fn load_at(token: &str, gist_id: &str, revision: Option<&str>) -> ApiResult<Self> {
+        let Some(sha) = revision else {
+            return Self::load(token, gist_id).await;
+        };
+        if sha.is_empty() || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(GistDbError::BadRequest(format!(
+                "Invalid revision '{}'",
+                sha
+            )));
+        }
+        let gist = get_gist_revision(token, gist_id, sha).await?;
+        let mut db = Self::from_gist(gist_id, gist)?;
+        db.revision = Some(sha.to_string());
+        Ok(db)
+    }
+
+    pub fn from_gist(gist_id: &str, gist: Value) -> ApiResult<Self> {
+        let manifest: Manifest = match gist["files"][MANIFEST_FILE]["content"].as_str() {
+            Some(content) => serde_json::from_str(content).map_err(|e| {
+                GistDbError::Internal(format!("Database manifest is invalid: {}", e))
+            })?,
+            None => legacy_manifest(&gist),
+        };
+        if manifest.format_version > FORMAT_VERSION {
+            return Err(GistDbError::conflict(format!(
+                "Database format version {} is newer than the supported version {}",
+                manifest.format_version, FORMAT_VERSION
+            )));
+        }
+
+        let trash = match gist["files"][TRASH_FILE]["content"].as_str() {
+            Some(content) => serde_json::from_str(content)
+                .map_err(|e| GistDbError::Internal(format!("Database trash is invalid: {}", e)))?,
+            None => Trash::default(),
+        };
+
+        Ok(Database {
+            gist_id: gist_id.to_string(),
+            gist,
+            manifest,
+            trash,
+            revision: None,
+            files: Map::new(),
+            manifest_changed: false,
+            trash_changed: false,
+        })
+    }
+
+    pub fn description(&self) -> &str {
+        self.gist["description"]
+            .as_str()
+            .unwrap_or("GistDB Database")
+    }
+
+    pub fn set_description(&mut self, description: &str) {
+        self.gist["description"] = Value::String(description.to_string());
+    }
+
+    pub fn mark_manifest_changed(&mut self) {
+        self.manifest_changed = true;
+    }
+
+    pub fn has_collection(&self, name: &str) -> bool {
+        self.manifest.collections.contains_key(name)
+    }
+
+    pub fn require_collection(&self, name: &str) -> ApiResult<&CollectionSettings> {
+        self.manifest
+            .collections
+            .get(name)
+            .ok_or_else(|| GistDbError::NotFound(format!("Collection '{}' not found", name)))
+    }
+
+    pub fn collection_names(&self) -> Vec<String> {
+        self.manifest.collections.keys().cloned().collect()
+    }
+
+    pub fn collection(&self, name: &str) -> ApiResult<Map<String, Value>> {
+        let settings = self.require_collection(name)?;
+        let mut data = self.stored_collection(name)?;
+        let now = chrono::Utc::now();
+        data.retain(|_, object| !settings.is_expired(object, now));
+        Ok(data)
+    }
+
+    /// The version this database was loaded at, from the gist's history.
+    pub fn version(&self) -> Option<&str> {
+        self.revision
+            .as_deref()
+            .or_else(|| self.gist["history"][0]["version"].as_str())
+    }
+
+    /// The earliest `expires_at` still ahead in a collection, after which
+    /// reads of it change without a new revision.
+    pub fn next_expiry(&self, name: &str) -> ApiResult<Option<DateTime<Utc>>> {
+        if self.require_collection(name)?.ttl.is_none() {
+            return Ok(None);
+        }
+        let now = chrono::Utc::now();
+        Ok(self
+            .stored_collection(name)?
+            .values()
+            .filter_map(expiry_of)
+            .filter(|expires_at| *expires_at > now)
+            .min())
+    }
+
+    fn stored_collection(&self, name: &str) -> ApiResult<Map<String, Value>> {
+        let filename = collection_file(name);
+        let file = self
+            .files
+            .get(&filename)
+            .unwrap_or(&self.gist["files"][&filename]);
+        let content = file["content"].as_str().unwrap_or("{}");
+        serde_json::from_str(content).map_err(|e| {
+            GistDbError::Internal(format!(
+                "Collection '{}' contains invalid JSON: {}",
+                name, e
+            ))
+        })
+    }
+
+    pub fn add_collection(&mut self, name: &str) -> ApiResult<()> {
+        validate_collection_name(name)?;
+        if self.has_collection(name) {
+            return Err(GistDbError::conflict(format!(
+                "Collection '{}' already exists",
+                name
+            )));
+        }
+
+        self.manifest.collections.insert(
+            name.to_string(),
+            CollectionSettings {
+                created_at: Some(chrono::Utc::now().to_rfc3339()),
+                ..Default::default()
+            },
+        );
+        self.manifest_changed = true;
+        if self.gist["files"].get(collection_file(name)).is_none() {
+            self.put_collection(name, &Map::new())?;
+        }
+        Ok(())
+    }
+
+    /// Removes a collection, applying the `on_delete` of every relation that
+    /// points at it from another collection.
+    pub fn remove_collection(&mut self, name: &str) -> ApiResult<Value> {
+        let settings = self.require_collection(name)?.clone();
+        let ids: Vec<String> = self.collection(name)?.keys().cloned().collect();
+        let changes = self.delete_related(name, &ids, Some(name))?;
+        if settings.soft_delete {
+            let trashed = TrashedCollection {
+                deleted_at: chrono::Utc::now().to_rfc3339(),
+                indexes: self
+                    .manifest
+                    .indexes
+                    .iter()
+                    .filter(|index| index.collection == name)
+                    .cloned()
+                    .collect(),
+                objects: self.collection(name)?,
+                settings,
+            };
+            self.trash.collections.insert(name.to_string(), trashed);
+            self.trash_changed = true;
+        }
+        self.manifest.collections.remove(name);
+        self.manifest
+            .indexes
+            .retain(|index| index.collection != name);
+        self.manifest
+            .relations
+            .retain(|relation| relation.collection != name && relation.references != name);
+        self.manifest_changed = true;
+        self.files.remove(&collection_file(name));
+        if self.gist["files"].get(collection_file(name)).is_some() {
+            self.files.insert(collection_file(name), Value::Null);
+        }
+        Ok(changes)
+    }
+
+    pub fn set_fields(
+        &mut self,
+        collection: &str,
+        defaults: Map<String, Value>,
+        computed: Vec<ComputedField>,
+    ) -> ApiResult<()> {
+        let paths = defaults
+            .keys()
+            .chain(computed.iter().flat_map(|c| [&c.field, &c.from]));
+        if let Some(path) = paths
+            .into_iter()
+            .find(|p| p.is_empty() || p.split('.').any(str::is_empty))
+        {
+            return Err(GistDbError::BadRequest(format!(
+                "Invalid field path '{}'",
+                path
+            )));
+        }
+
+        let settings = self
+            .manifest
+            .collections
+            .get_mut(collection)
+            .ok_or_else(|| {
+                GistDbError::NotFound(format!("Collection '{}' not found", collection))
+            })?;
+        settings.defaults = defaults;
+        settings.computed = computed;
+        self.manifest_changed = true;
+        Ok(())
+    }
+
+    pub fn set_schema(&mut self, collection: &str, schema: Option<Value>) -> ApiResult<()> {
+        if let Some(schema) = &schema {
+            Schema::parse(schema)?;
+        }
+        self.manifest
+            .collections
+            .get_mut(collection)
+            .ok_or_else(|| GistDbError::NotFound(format!("Collection '{}' not found", collection)))?
+            .schema = schema;
+        self.manifest_changed = true;
+        Ok(())
+    }
+
+    pub fn write_object(
+        &self,
+        collection: &str,
+        data: &mut Map<String, Value>,
+        object_id: &str,
+        object: Value,
+        policy: Option<&Filter>,
+    ) -> ApiResult<Value> {
+        self.object_writer(collection, data)?
+            .write(data, object_id, object, policy)
+    }
+
+    pub fn object_writer<'a>(
+        &'a self,
+        collection: &'a str,
+        data: &Map<String, Value>,
+    ) -> ApiResult<ObjectWriter<'a>> {
+        ObjectWriter::new(&self.manifest, collection, data)
+    }
+
+    /// Inserts imported rows into `data`, generating missing ids. Ids that
+    /// already exist, in `data` or earlier rows, are rejected.
+    pub fn insert_rows(
+        &self,
+        collection: &str,
+        data: &mut Map<String, Value>,
+        rows: Vec<(Option<String>, Value)>,
+    ) -> ApiResult<Vec<String>> {
+        let mut writer = self.object_writer(collection, data)?;
+        let mut object_ids = Vec::with_capacity(rows.len());
+        for (i, (object_id, object)) in rows.into_iter().enumerate() {
+            let object_id = object_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+            if data.contains_key(&object_id) {
+                return Err(GistDbError::conflict(format!(
+                    "Object '{}' already exists",
+                    object_id
+                ))
+                .at_row(i + 1));
+            }
+            writer
+                .write(data, &object_id, object, None)
+                .map_err(|e| e.at_row(i + 1))?;
+            object_ids.push(object_id);
+        }
+        Ok(object_ids)
+    }
+
+    pub fn add_index(&mut self, index: IndexDefinition) -> ApiResult<()> {
+        self.require_collection(&index.collection)?;
+        if index.fields.is_empty() || index.fields.iter().any(|f| f.is_empty()) {
+            return Err(GistDbError::BadRequest(
+                "An index needs at least one non-empty field path".to_string(),
+            ));
+        }
+        if self
+            .manifest
+            .indexes
+            .iter()
+            .any(|i| i.collection == index.collection && i.name == index.name)
+        {
+            return Err(GistDbError::conflict(format!(
+                "Index '{}' already exists on '{}'",
+                index.name, index.collection
+            )));
+        }
+
+        if index.unique {
+            let mut seen: HashMap<String, &String> = HashMap::new();
+            let data = self.collection(&index.collection)?;
+            for (object_id, object) in &data {
+                let Some(key) = index.key(object) else {
+                    continue;
+                };
+                if let Some(existing) = seen.insert(Value::from(key).to_string(), object_id) {
+                    return Err(GistDbError::Conflict {
+                        message: format!("Existing objects violate unique index '{}'", index.name),
+                        details: Some(json!({
+                            "index": index.name,
+                            "fields": index.fields,
+                            "object_ids": [existing, object_id],
+                        })),
+                    });
+                }
+            }
+        }
+
+        self.manifest.indexes.push(index);
+        self.manifest_changed = true;
+        Ok(())
+    }
+
+    pub fn remove_index(&mut self, collection: &str, name: &str) -> ApiResult<()> {
+        let before = self.manifest.indexes.len();
+        self.manifest
+            .indexes
+            .retain(|i| !(i.collection == collection && i.name == name));
+        if self.manifest.indexes.len() == before {
+            return Err(GistDbError::NotFound(format!(
+                "Index '{}' not found on '{}'",
+                name, collection
+            )));
+        }
+        self.manifest_changed = true;
+        Ok(())
+    }
+
+    pub fn add_relation(&mut self, relation: Relation) -> ApiResult<()> {
+        self.require_collection(&relation.collection)?;
+        self.require_collection(&relation.references)?;
+        if relation.field.is_empty() || relation.field.split('.').any(str::is_empty) {
+            return Err(GistDbError::BadRequest(format!(
+                "Invalid field path '{}'",
+                relation.field
+            )));
+        }
+        if self
+            .manifest
+            .relations
+            .iter()
+            .any(|r| r.name == relation.name)
+        {
+            return Err(GistDbError::conflict(format!(
+                "Relation '{}' already exists",
+                relation.name
+            )));
+        }
+
+        self.manifest.relations.push(relation);
+        self.manifest_changed = true;
+        Ok(())
+    }
+
+    pub fn remove_relation(&mut self, name: &str) -> ApiResult<()> {
+        let before = self.manifest.relations.len();
+        self.manifest.relations.retain(|r| r.name != name);
+        if self.manifest.relations.len() == before {
+            return Err(GistDbError::NotFound(format!(
+                "Relation '{}' not found",
+                name
+            )));
+        }
+        self.manifest_changed = true;
+        Ok(())
+    }
+
+    fn trash_object(&mut self, collection: &str, id: &str, data: Value) {
+        let soft_delete = self
+            .manifest
+            .collections
+            .get(collection)
+            .is_some_and(|settings| settings.soft_delete);
+        if !soft_delete {
+            return;
+        }
+        self.trash
+            .objects
+            .entry(collection.to_string())
+            .or_default()
+            .insert(
+                id.to_string(),
+                TrashedObject {
+                    deleted_at: chrono::Utc::now().to_rfc3339(),
+                    data,
+                },
+            );
+        self.trash_changed = true;
+    }
+
+    pub fn set_soft_delete(
+        &mut self,
+        collection: &str,
+        soft_delete: bool,
+        retention_days: Option<u32>,
+    ) -> ApiResult<()> {
+        let settings = self
+            .manifest
+            .collections
+            .get_mut(collection)
+            .ok_or_else(|| {
+                GistDbError::NotFound(format!("Collection '{}' not found", collection))
+            })?;
+        settings.soft_delete = soft_delete;
+        settings.retention_days = retention_days;
+        self.manifest_changed = true;
+        Ok(())
+    }
+
+    pub fn restore_object(
+        &mut self,
+        collection: &str,
+        id: &str,
+        policy: Option<&Filter>,
+    ) -> ApiResult<Value> {
+        let trashed = self
+            .trash
+            .objects
+            .get(collection)
+            .and_then(|objects| objects.get(id))
+            .cloned()
+            .ok_or_else(|| {
+                GistDbError::NotFound(format!(
+                    "Object '{}' is not in the trash of '{}'",
+                    id, collection
+                ))
+            })?;
+
+        let mut data = self.collection(collection)?;
+        if data.contains_key(id) {
+            return Err(GistDbError::conflict(format!(
+                "Object '{}' already exists in '{}'",
+                id, collection
+            )));
+        }
+        let restored = self.write_object(collection, &mut data, id, trashed.data, policy)?;
+        self.put_collection(collection, &data)?;
+
+        if let Some(objects) = self.trash.objects.get_mut(collection) {
+            objects.remove(id);
+            if objects.is_empty() {
+                self.trash.objects.remove(collection);
+            }
+        }
+        self.trash_changed = true;
+        Ok(restored)
+    }
+
+    pub fn restore_collection(&mut self, name: &str) -> ApiResult<usize> {
+        if self.has_collection(name) {
+            return Err(GistDbError::conflict(format!(
+                "Collection '{}' already exists",
+                name
+            )));
+        }
+        let trashed = self.trash.collections.remove(name).ok_or_else(|| {
+            GistDbError::NotFound(format!("Collection '{}' is not in the trash", name))
+        })?;
+
+        self.manifest
+            .collections
+            .insert(name.to_string(), trashed.settings);
+        self.manifest.indexes.extend(trashed.indexes);
+        self.put_collection(name, &trashed.objects)?;
+        self.manifest_changed = true;
+        self.trash_changed = true;
+        Ok(trashed.objects.len())
+    }
+
+    pub fn purge_trash(&mut self, collection: Option<&str>, id: Option<&str>) -> ApiResult<usize> {
+        let purged = match (collection, id) {
+            (None, Some(_)) => {
+                return Err(GistDbError::BadRequest(
+                    "'object_id' requires 'collection_name'".to_string(),
+                ))
+            }
+            (None, None) => {
+                let count = self
+                    .trash
+                    .objects
+                    .values()
+                    .map(BTreeMap::len)
+                    .sum::<usize>()
+                    + self.trash.collections.len();
+                self.trash = Trash::default();
+                count
+            }
+            (Some(collection), Some(id)) => {
+                let objects = self.trash.objects.get_mut(collection);
+                if objects.and_then(|objects| objects.remove(id)).is_none() {
+                    return Err(GistDbError::NotFound(format!(
+                        "Object '{}' is not in the trash of '{}'",
+                        id, collection
+                    )));
+                }
+                1
+            }
+            (Some(collection), None) => {
+                self.trash
+                    .objects
+                    .remove(collection)
+                    .map_or(0, |objects| objects.len())
+                    + usize::from(self.trash.collections.remove(collection).is_some())
+            }
+        };
+        self.trash.objects.retain(|_, objects| !objects.is_empty());
+        self.trash_changed = true;
+        Ok(purged)
+    }
+
+    pub fn set_ttl(&mut self, collection: &str, ttl: Option<TtlSettings>) -> ApiResult<()> {
+        self.manifest
+            .collections
+            .get_mut(collection)
+            .ok_or_else(|| GistDbError::NotFound(format!("Collection '{}' not found", collection)))?
+            .ttl = ttl;
+        self.manifest_changed = true;
+        Ok(())
+    }
+
+    pub fn purge_expired_objects(&mut self) -> ApiResult<usize> {
+        let mut purged = 0;
+        for name in self.collection_names() {
+            let stored = self.stored_collection(&name)?;
+            let live = self.collection(&name)?;
+            if live.len() < stored.len() {
+                purged += stored.len() - live.len();
+                self.put_collection(&name, &live)?;
+            }
+        }
+        Ok(purged)
+    }
+
+    pub fn purge_expired_trash(&mut self) -> usize {
+        let now = chrono::Utc::now();
+        let mut purged = 0;
+
+        for (collection, objects) in self.trash.objects.iter_mut() {
+            let retention = self
+                .manifest
+                .collections
+                .get(collection)
+                .or_else(|| self.trash.collections.get(collection).map(|c| &c.settings))
+                .and_then(|settings| settings.retention_days);
+            let before = objects.len();
+            objects.retain(|_, object| !is_expired(&object.deleted_at, retention, now));
+            purged += before - objects.len();
+        }
+        self.trash.objects.retain(|_, objects| !objects.is_empty());
+
+        let before = self.trash.collections.len();
+        self.trash.collections.retain(|_, collection| {
+            !is_expired(
+                &collection.deleted_at,
+                collection.settings.retention_days,
+                now,
+            )
+        });
+        purged += before - self.trash.collections.len();
+
+        if purged > 0 {
+            self.trash_changed = true;
+        }
+        purged
+    }
+
+    fn working_collection<'a>(
+        &self,
+        working: &'a mut HashMap<String, Map<String, Value>>,
+        name: &str,
+    ) -> ApiResult<&'a mut Map<String, Value>> {
+        if !working.contains_key(name) {
+            working.insert(name.to_string(), self.collection(name)?);
+        }
+        Ok(working.get_mut(name).expect("collection was just loaded"))
+    }
+
+    pub fn delete_objects(&mut self, collection: &str, ids: &[String]) -> ApiResult<Value> {
+        self.delete_related(collection, ids, None)
+    }
+
+    /// `dropping` names a collection that is being removed as a whole: its
+    /// objects are not trashed one by one or written back, and references
+    /// from inside it do not hold anything up.
+    fn delete_related(
+        &mut self,
+        collection: &str,
+        ids: &[String],
+        dropping: Option<&str>,
+    ) -> ApiResult<Value> {
+        let mut working: HashMap<String, Map<String, Value>> = HashMap::new();
+        let mut deleted: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut updated: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        let mut queue: VecDeque<(String, String)> = ids
+            .iter()
+            .map(|id| (collection.to_string(), id.clone()))
+            .collect();
+
+        while let Some((collection, id)) = queue.pop_front() {
+            let Some(object) = self
+                .working_collection(&mut working, &collection)?
+                .remove(&id)
+            else {
+                continue;
+            };
+            if dropping != Some(collection.as_str()) {
+                self.trash_object(&collection, &id, object);
+                deleted
+                    .entry(collection.clone())
+                    .or_default()
+                    .push(id.clone());
+            }
+
+            let relations: Vec<Relation> = self
+                .manifest
+                .relations
+                .iter()
+                .filter(|r| r.references == collection && dropping != Some(r.collection.as_str()))
+                .cloned()
+                .collect();
+            for relation in relations {
+                let children = self.working_collection(&mut working, &relation.collection)?;
+                let referencing: Vec<String> = children
+                    .iter()
+                    .filter(|(_, child)| relation.refers_to(child, &id))
+                    .map(|(child_id, _)| child_id.clone())
+                    .collect();
+                if referencing.is_empty() {
+                    continue;
+                }
+
+                match relation.on_delete {
+                    OnDelete::Restrict => {
+                        return Err(GistDbError::Conflict {
+                            message: format!(
+                                "Object '{}' in '{}' is still referenced by '{}' (relation '{}')",
+                                id, collection, relation.collection, relation.name
+                            ),
+                            details: Some(json!({
+                                "relation": relation.name,
+                                "collection": relation.collection,
+                                "object_ids": referencing,
+                            })),
+                        });
+                    }
+                    OnDelete::Cascade => {
+                        queue.extend(
+                            referencing
+                                .into_iter()
+                                .map(|child_id| (relation.collection.clone(), child_id)),
+                        );
+                    }
+                    OnDelete::SetNull => {
+                        for child_id in referencing {
+                            if let Some(child) = children.get_mut(&child_id) {
+                                relation.clear_reference(child, &id);
+                            }
+                            updated
+                                .entry(relation.collection.clone())
+                                .or_default()
+                                .insert(child_id);
+                        }
+                    }
+                }
+            }
+        }
+
+        for (collection, data) in &working {
+            if dropping != Some(collection.as_str()) {
+                self.put_collection(collection, data)?;
+            }
+        }
+        for (collection, ids) in &deleted {
+            if let Some(set) = updated.get_mut(collection) {
+                set.retain(|id| !ids.contains(id));
+            }
+        }
+        updated.retain(|_, ids| !ids.is_empty());
+
+        Ok(json!({ "deleted": deleted, "updated": updated }))
+    }
+
+    fn collection_definition(&self, name: &str) -> ApiResult<Value> {
+        let indexes: Vec<&IndexDefinition> = self
+            .manifest
+            .indexes
+            .iter()
+            .filter(|index| index.collection == name)
+            .collect();
+        Ok(json!({
+            "settings": self.manifest.collections.get(name),
+            "indexes": serde_json::to_value(indexes)?,
+        }))
+    }
+
+    fn collection_changes(
+        current: &Map<String, Value>,
+        target: &Map<String, Value>,
+    ) -> Option<Value> {
+        let created: Vec<&String> = target
+            .keys()
+            .filter(|id| !current.contains_key(*id))
+            .collect();
+        let deleted: Vec<&String> = current
+            .keys()
+            .filter(|id| !target.contains_key(*id))
+            .collect();
+        let updated: Vec<Value> = target
+            .iter()
+            .filter_map(|(id, object)| {
+                let old = current.get(id).filter(|old| *old != object)?;
+                Some(json!({ "object_id": id, "diff": diff(old, object) }))
+            })
+            .collect();
+        if created.is_empty() && deleted.is_empty() && updated.is_empty() {
+            return None;
+        }
+        Some(json!({ "created": created, "deleted": deleted, "updated": updated }))
+    }
+
+    /// Stages the state of `target` (a historical load of the same gist), either
+    /// for the whole database or only for `collections`, and returns the changes
+    /// per collection.
+    pub fn rollback_to(
+        &mut self,
+        target: &Database,
+        collections: Option<&[String]>,
+    ) -> ApiResult<Value> {
+        let version = target.revision.as_deref().unwrap_or_default();
+        let names: BTreeSet<String> = match collections {
+            Some(names) => {
+                for name in names {
+                    if !target.has_collection(name) {
+                        return Err(GistDbError::NotFound(format!(
+                            "Collection '{}' does not exist at revision '{}'",
+                            name, version
+                        )));
+                    }
+                }
+                names.iter().cloned().collect()
+            }
+            None => self
+                .collection_names()
+                .into_iter()
+                .chain(target.collection_names())
+                .collect(),
+        };
+
+        let mut changes = Map::new();
+        for name in &names {
+            let current = if self.has_collection(name) {
+                Some(self.stored_collection(name)?)
+            } else {
+                None
+            };
+            let data = if target.has_collection(name) {
+                Some(target.stored_collection(name)?)
+            } else {
+                None
+            };
+            let settings_changed =
+                self.collection_definition(name)? != target.collection_definition(name)?;
+
+            let change = match (&current, &data) {
+                (None, Some(data)) => Some(json!({
+                    "status": "created",
+                    "objects": data.len(),
+                })),
+                (Some(current), None) => Some(json!({
+                    "status": "removed",
+                    "objects": current.len(),
+                })),
+                (Some(current), Some(data)) => match Self::collection_changes(current, data) {
+                    Some(mut objects) => {
+                        objects["status"] = json!("modified");
+                        objects["settings_changed"] = json!(settings_changed);
+                        Some(objects)
+                    }
+                    None if settings_changed => Some(json!({
+                        "status": "modified",
+                        "settings_changed": true,
+                    })),
+                    None => None,
+                },
+                (None, None) => None,
+            };
+            let Some(change) = change else {
+                continue;
+            };
+            changes.insert(name.clone(), change);
+
+            match data {
+                Some(data) => self.put_collection(name, &data)?,
+                None => {
+                    if self.gist["files"].get(collection_file(name)).is_some() {
+                        self.files.insert(collection_file(name), Value::Null);
+                    }
+                }
+            }
+        }
+
+        match collections {
+            Some(names) => {
+                for name in names {
+                    if let Some(settings) = target.manifest.collections.get(name) {
+                        self.manifest
+                            .collections
+                            .insert(name.clone(), settings.clone());
+                    }
+                    self.manifest
+                        .indexes
+                        .retain(|index| &index.collection != name);
+                    self.manifest.indexes.extend(
+                        target
+                            .manifest
+                            .indexes
+                            .iter()
+                            .filter(|index| &index.collection == name)
+                            .cloned(),
+                    );
+                }
+            }
+            None => {
+                self.manifest = target.manifest.clone();
+                self.trash = target.trash.clone();
+                self.trash_changed = true;
+            }
+        }
+        self.manifest_changed = true;
+
+        Ok(Value::Object(changes))
+    }
+
+    pub fn export(mut self) -> ApiResult<Bundle> {
+        migration_service::migrate(&mut self)?;
+        let collections = self
+            .collection_names()
+            .into_iter()
+            .map(|name| Ok((name.clone(), self.collection(&name)?)))
+            .collect::<ApiResult<_>>()?;
+
+        Ok(Bundle {
+            format: BUNDLE_FORMAT.to_string(),
+            bundle_version: BUNDLE_VERSION,
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            manifest: self.manifest,
+            collections,
+            trash: self.trash,
+        })
+    }
+
+    pub fn put_collection(&mut self, name: &str, data: &Map<String, Value>) -> ApiResult<()> {
+        self.files.insert(
+            collection_file(name),
+            json!({ "content": serde_json::to_string(data)? }),
+        );
+        Ok(())
+    }
+
+    pub async fn commit(&mut self, token: &str) -> ApiResult<Value> {
+        if let Some(revision) = &self.revision {
+            return Err(GistDbError::Internal(format!(
+                "Cannot write to historical revision '{}'",
+                revision
+            )));
+        }
+        migration_service::migrate(self)?;
+
+        let mut files = std::mem::take(&mut self.files);
+        if self.manifest_changed {
+            files.insert(
+                MANIFEST_FILE.to_string(),
+                json!({ "content": serde_json::to_string_pretty(&self.manifest)? }),
+            );
+            self.manifest_changed = false;
+        }
+        if self.trash_changed {
+            files.insert(
+                TRASH_FILE.to_string(),
+                json!({ "content": serde_json::to_string_pretty(&self.trash)? }),
+            );
+            self.trash_changed = false;
+        }
+
+        let body = json!({
+            "description": self.description(),
+            "files": files,
+        });
+        patch_gist(token, &self.gist_id, body).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database(collections: Value, objects: Value, trash: Value) -> Database {
+        let manifest = json!({
+            "format_version": FORMAT_VERSION,
+            "name": "test",
+            "created_at": "2025-01-01T00:00:00Z",
+            "collections": collections,
+        });
+        let mut files = Map::new();
+        files.insert(
+            MANIFEST_FILE.to_string(),
+            json!({ "content": manifest.to_string() }),
+        );
+        files.insert(
+            TRASH_FILE.to_string(),
+            json!({ "content": trash.to_string() }),
+        );
+        for (name, data) in objects.as_object().unwrap() {
+            files.insert(
+                collection_file(name),
+                json!({ "content": data.to_string() }),
+            );
+        }
+        Database::from_gist(
+            "gist",
+            json!({ "description": "GistDB: test", "files": files }),
+        )
+        .unwrap()
+    }
+
+    fn empty_trash() -> Value {
+        json!({ "objects": {}, "collections": {} })
+    }
+
+    #[test]
+    fn purges_objects_past_their_own_expiry() {
+        let mut db = database(
+            json!({ "items": { "ttl": {} } }),
+            json!({ "items": {
+                "past": { "expires_at": "2000-01-01T00:00:00Z" },
+                "past_unix": { "expires_at": 946684800 },
+                "future": { "expires_at": "2999-01-01T00:00:00Z" },
+                "forever": { "name": "kept" },
+            }}),
+            empty_trash(),
+        );
+
+        assert_eq!(db.purge_expired_objects().unwrap(), 2);
+        let mut ids: Vec<String> = db.collection("items").unwrap().keys().cloned().collect();
+        ids.sort();
+        assert_eq!(ids, ["forever", "future"]);
+        assert_eq!(db.stored_collection("items").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn reads_hide_expired_objects_before_the_sweep() {
+        let db = database(
+            json!({ "items": { "ttl": {} } }),
+            json!({ "items": {
+                "past": { "expires_at": "2000-01-01T00:00:00Z" },
+                "future": { "expires_at": "2999-01-01T00:00:00Z" },
+            }}),
+            empty_trash(),
+        );
+
+        assert!(!db.collection("items").unwrap().contains_key("past"));
+        assert_eq!(db.stored_collection("items").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn purge_without_expired_objects_changes_nothing() {
+        let mut db = database(
+            json!({ "items": { "ttl": {} } }),
+            json!({ "items": { "a": { "expires_at": "2999-01-01T00:00:00Z" } } }),
+            empty_trash(),
+        );
+
+        assert_eq!(db.purge_expired_objects().unwrap(), 0);
+        assert!(db.files.is_empty());
+    }
+
+    #[test]
+    fn expires_at_is_an_ordinary_field_without_ttl() {
+        let mut db = database(
+            json!({ "coupons": {} }),
+            json!({ "coupons": { "old": { "expires_at": "2000-01-01T00:00:00Z" } } }),
+            empty_trash(),
+        );
+
+        assert!(db.collection("coupons").unwrap().contains_key("old"));
+        assert_eq!(db.next_expiry("coupons").unwrap(), None);
+        assert_eq!(db.purge_expired_objects().unwrap(), 0);
+        assert!(db.files.is_empty());
+    }
+
+    #[test]
+    fn purges_trash_past_retention() {
+        let mut db = database(
+            json!({ "items": { "soft_delete": true, "retention_days": 7 }, "notes": { "soft_delete": true } }),
+            json!({ "items": {}, "notes": {} }),
+            json!({
+                "objects": {
+                    "items": {
+                        "old": { "deleted_at": "2000-01-01T00:00:00Z", "data": {} },
+                        "recent": { "deleted_at": "2999-01-01T00:00:00Z", "data": {} },
+                    },
+                    "notes": {
+                        "old": { "deleted_at": "2000-01-01T00:00:00Z", "data": {} },
+                    },
+                },
+                "collections": {
+                    "logs": {
+                        "deleted_at": "2000-01-01T00:00:00Z",
+                        "settings": { "retention_days": 1 },
+                        "objects": {},
+                    },
+                    "archive": {
+                        "deleted_at": "2000-01-01T00:00:00Z",
+                        "settings": {},
+                        "objects": {},
+                    },
+                },
+            }),
+        );
+
+        assert_eq!(db.purge_expired_trash(), 2);
+        assert_eq!(
+            db.trash.objects["items"].keys().collect::<Vec<_>>(),
+            ["recent"]
+        );
+        assert!(db.trash.objects["notes"].contains_key("old"));
+        assert_eq!(db.trash.collections.keys().collect::<Vec<_>>(), ["archive"]);
+    }
+
+    fn relate(db: &mut Database, collection: &str, field: &str, references: &str, on_delete: &str) {
+        db.manifest.relations.push(
+            serde_json::from_value(json!({
+                "name": format!("{}_{}", collection, field),
+                "collection": collection,
+                "field": field,
+                "references": references,
+                "on_delete": on_delete,
+            }))
+            .unwrap(),
+        );
+    }
+
+    fn ids(db: &Database, collection: &str) -> Vec<String> {
+        db.collection(collection).unwrap().keys().cloned().collect()
+    }
+
+    fn users_and_orders(on_delete: &str) -> Database {
+        let mut db = database(
+            json!({ "users": {}, "orders": {} }),
+            json!({
+                "users": { "u1": {}, "u2": {} },
+                "orders": { "o1": { "user_id": "u1" }, "o2": { "user_id": "u2" } },
+            }),
+            empty_trash(),
+        );
+        relate(&mut db, "orders", "user_id", "users", on_delete);
+        db
+    }
+
+    #[test]
+    fn restrict_blocks_deleting_referenced_objects() {
+        let mut db = users_and_orders("restrict");
+        let err = db.delete_objects("users", &["u1".to_string()]).unwrap_err();
+        assert!(matches!(
+            err,
+            GistDbError::Conflict { details: Some(details), .. }
+                if details["object_ids"] == json!(["o1"])
+        ));
+
+        db.delete_objects("orders", &["o2".to_string()]).unwrap();
+        let changes = db.delete_objects("users", &["u2".to_string()]).unwrap();
+        assert_eq!(changes["deleted"], json!({ "users": ["u2"] }));
+    }
+
+    #[test]
+    fn cascade_follows_relations_transitively() {
+        let mut db = database(
+            json!({ "users": {}, "orders": {}, "lines": {} }),
+            json!({
+                "users": { "u1": {}, "u2": {} },
+                "orders": { "o1": { "user_id": "u1" }, "o2": { "user_id": "u2" } },
+                "lines": { "l1": { "order_id": "o1" }, "l2": { "order_id": "o2" } },
+            }),
+            empty_trash(),
+        );
+        relate(&mut db, "orders", "user_id", "users", "cascade");
+        relate(&mut db, "lines", "order_id", "orders", "cascade");
+
+        let changes = db.delete_objects("users", &["u1".to_string()]).unwrap();
+        assert_eq!(
+            changes,
+            json!({
+                "deleted": { "users": ["u1"], "orders": ["o1"], "lines": ["l1"] },
+                "updated": {},
+            })
+        );
+        assert_eq!(ids(&db, "orders"), ["o2"]);
+        assert_eq!(ids(&db, "lines"), ["l2"]);
+    }
+
+    #[test]
+    fn set_null_clears_scalar_and_array_references() {
+        let mut db = database(
+            json!({ "users": {}, "orders": {}, "teams": {} }),
+            json!({
+                "users": { "u1": {}, "u2": {} },
+                "orders": { "o1": { "user_id": "u1" } },
+                "teams": { "t1": { "members": ["u1", "u2"] } },
+            }),
+            empty_trash(),
+        );
+        relate(&mut db, "orders", "user_id", "users", "set_null");
+        relate(&mut db, "teams", "members", "users", "set_null");
+
+        let changes = db.delete_objects("users", &["u1".to_string()]).unwrap();
+        assert_eq!(
+            changes["updated"],
+            json!({ "orders": ["o1"], "teams": ["t1"] })
+        );
+        assert_eq!(
+            db.collection("orders").unwrap()["o1"],
+            json!({ "user_id": null })
+        );
+        assert_eq!(
+            db.collection("teams").unwrap()["t1"],
+            json!({ "members": ["u2"] })
+        );
+    }
+
+    #[test]
+    fn restrict_applies_to_array_references() {
+        let mut db = database(
+            json!({ "users": {}, "teams": {} }),
+            json!({
+                "users": { "u1": {}, "u2": {} },
+                "teams": { "t1": { "members": ["u2"] } },
+            }),
+            empty_trash(),
+        );
+        relate(&mut db, "teams", "members", "users", "restrict");
+
+        assert!(db.delete_objects("users", &["u2".to_string()]).is_err());
+        db.delete_objects("users", &["u1".to_string()]).unwrap();
+        assert_eq!(ids(&db, "users"), ["u2"]);
+    }
+
+    #[test]
+    fn cascade_through_self_references_terminates() {
+        let mut db = database(
+            json!({ "people": {} }),
+            json!({ "people": {
+                "a": { "manager": "c" },
+                "b": { "manager": "a" },
+                "c": { "manager": "b" },
+                "d": {},
+            }}),
+            empty_trash(),
+        );
+        relate(&mut db, "people", "manager", "people", "cascade");
+
+        let changes = db.delete_objects("people", &["a".to_string()]).unwrap();
+        assert_eq!(changes["deleted"], json!({ "people": ["a", "b", "c"] }));
+        assert_eq!(ids(&db, "people"), ["d"]);
+    }
+
+    #[test]
+    fn removing_a_collection_honours_restrict() {
+        let mut db = users_and_orders("restrict");
+        assert!(matches!(
+            db.remove_collection("users"),
+            Err(GistDbError::Conflict { .. })
+        ));
+        assert!(db.has_collection("users"));
+        assert_eq!(db.manifest.relations.len(), 1);
+    }
+
+    #[test]
+    fn removing_a_collection_cascades_and_sets_null() {
+        let mut db = users_and_orders("cascade");
+        let changes = db.remove_collection("users").unwrap();
+        assert_eq!(changes["deleted"], json!({ "orders": ["o1", "o2"] }));
+        assert!(ids(&db, "orders").is_empty());
+        assert!(db.manifest.relations.is_empty());
+
+        let mut db = users_and_orders("set_null");
+        db.remove_collection("users").unwrap();
+        assert_eq!(
+            db.collection("orders").unwrap()["o2"],
+            json!({ "user_id": null })
+        );
+    }
+
+    #[test]
+    fn removing_a_collection_ignores_its_own_references() {
+        let mut db = database(
+            json!({ "people": {} }),
+            json!({ "people": { "a": {}, "b": { "manager": "a" } } }),
+            empty_trash(),
+        );
+        relate(&mut db, "people", "manager", "people", "restrict");
+
+        db.remove_collection("people").unwrap();
+        assert!(!db.has_collection("people"));
+    }
+
+    fn with_unique_index(fields: Value) -> Database {
+        let mut db = database(
+            json!({ "seats": {} }),
+            json!({ "seats": {
+                "a": { "row": 1, "seat": 1 },
+                "b": { "row": 1, "seat": 2 },
+            }}),
+            empty_trash(),
+        );
+        db.manifest.indexes.push(
+            serde_json::from_value(json!({
+                "name": "place",
+                "collection": "seats",
+                "fields": fields,
+                "unique": true,
+            }))
+            .unwrap(),
+        );
+        db
+    }
+
+    #[test]
+    fn unique_compound_keys_compare_all_fields() {
+        let db = with_unique_index(json!(["row", "seat"]));
+        let mut data = db.collection("seats").unwrap();
+        let mut writer = db.object_writer("seats", &data).unwrap();
+
+        writer
+            .write(&mut data, "c", json!({ "row": 2, "seat": 1 }), None)
+            .unwrap();
+        writer
+            .write(&mut data, "d", json!({ "row": 1 }), None)
+            .unwrap();
+        let err = writer
+            .write(&mut data, "e", json!({ "row": 2, "seat": 1 }), None)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            GistDbError::Conflict { details: Some(details), .. }
+                if details["conflicting_id"] == "c"
+        ));
+    }
+
+    #[test]
+    fn unique_updates_keep_or_release_their_key() {
+        let db = with_unique_index(json!(["seat"]));
+        let mut data = db.collection("seats").unwrap();
+        let mut writer = db.object_writer("seats", &data).unwrap();
+
+        writer
+            .write(&mut data, "a", json!({ "row": 3, "seat": 1 }), None)
+            .unwrap();
+        writer
+            .write(&mut data, "a", json!({ "row": 3, "seat": 5 }), None)
+            .unwrap();
+        writer
+            .write(&mut data, "c", json!({ "seat": 1 }), None)
+            .unwrap();
+        assert!(writer
+            .write(&mut data, "d", json!({ "seat": 5 }), None)
+            .is_err());
+        assert!(writer
+            .write(&mut data, "b", json!({ "seat": 1 }), None)
+            .is_err());
+        assert_eq!(data["b"], json!({ "row": 1, "seat": 2 }));
+    }
+
+    #[test]
+    fn restore_checks_unique_indexes() {
+        let mut db = with_unique_index(json!(["seat"]));
+        db.trash.objects.insert(
+            "seats".to_string(),
+            serde_json::from_value(json!({
+                "old": { "deleted_at": "2025-01-01T00:00:00Z", "data": { "seat": 2 } },
+                "free": { "deleted_at": "2025-01-01T00:00:00Z", "data": { "seat": 9 } },
+            }))
+            .unwrap(),
+        );
+
+        assert!(matches!(
+            db.restore_object("seats", "old", None),
+            Err(GistDbError::Conflict { .. })
+        ));
+        db.restore_object("seats", "free", None).unwrap();
+        assert_eq!(ids(&db, "seats"), ["a", "b", "free"]);
+        assert!(!db.trash.objects["seats"].contains_key("free"));
+    }
+
+    #[test]
+    fn bundled_objects_must_meet_their_own_constraints() {
+        let mut manifest: Manifest = serde_json::from_value(json!({
+            "format_version": FORMAT_VERSION,
+            "name": "test",
+            "created_at": "2025-01-01T00:00:00Z",
+            "collections": { "users": {
+                "schema": { "required": ["email"] },
+                "defaults": { "active": true },
+            }},
+            "indexes": [{ "name": "email", "collection": "users", "fields": ["email"], "unique": true }],
+        }))
+        .unwrap();
+        let objects = |value: Value| value.as_object().unwrap().clone();
+
+        let checked = checked_objects(
+            &manifest,
+            "users",
+            objects(json!({ "a": { "email": "a@x" }, "b": { "email": "b@x" } })),
+        )
+        .unwrap();
+        assert_eq!(checked["a"], json!({ "email": "a@x", "active": true }));
+
+        let err = checked_objects(&manifest, "users", objects(json!({ "a": {} }))).unwrap_err();
+        assert!(
+            matches!(err, GistDbError::Validation { message, .. } if message.starts_with("Object 'a' in 'users'"))
+        );
+
+        let err = checked_objects(
+            &manifest,
+            "users",
+            objects(json!({ "a": { "email": "a@x" }, "b": { "email": "a@x" } })),
+        )
+        .unwrap_err();
+        assert!(matches!(err, GistDbError::Conflict { .. }));
+
+        manifest.indexes.clear();
+        assert!(checked_objects(
+            &manifest,
+            "users",
+            objects(json!({ "a": { "email": "a@x" }, "b": { "email": "a@x" } })),
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn imported_rows_reject_duplicate_ids() {
+        let db = database(
+            json!({ "users": {} }),
+            json!({ "users": { "a": {} } }),
+            empty_trash(),
+        );
+        let mut data = db.collection("users").unwrap();
+
+        let err = db
+            .insert_rows(
+                "users",
+                &mut data,
+                vec![
+                    (Some("b".to_string()), json!({})),
+                    (Some("b".to_string()), json!({})),
+                ],
+            )
+            .unwrap_err();
+        assert!(
+            matches!(err, GistDbError::Conflict { message, .. } if message.starts_with("Row 2:"))
+        );
+
+        let mut data = db.collection("users").unwrap();
+        assert!(db
+            .insert_rows("users", &mut data, vec![(Some("a".to_string()), json!({}))])
+            .is_err());
+        let ids = db
+            .insert_rows("users", &mut data, vec![(None, json!({ "n": 1 }))])
+            .unwrap();
+        assert_eq!(data[&ids[0]], json!({ "n": 1 }));
+    }
+}
@@ -1,20 +1,81 @@
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::services::rate_limit_service;
 use serde_json::{json, Value};
-use worker::{Fetch, Method, Request, RequestInit, Result};
+use std::time::Duration;
+use worker::{js_sys, Delay, Fetch, Headers, Method, Request, RequestInit};
+
+pub const GITHUB_API: &str = "https://api.github.com";
+
+const MAX_RETRIES: u32 = 3;
+const MAX_RETRY_WAIT_SECS: u64 = 10;
+const BASE_BACKOFF_MS: f64 = 250.0;
 
 pub async fn github_request(
     token: &str,
     method: Method,
     url: &str,
     body: Option<Value>,
-) -> Result<Value> {
+) -> ApiResult<Value> {
+    github_request_with_headers(token, method, url, body)
+        .await
+        .map(|(body, _)| body)
+}
+
+pub async fn github_request_with_headers(
+    token: &str,
+    method: Method,
+    url: &str,
+    body: Option<Value>,
+) -> ApiResult<(Value, Headers)> {
+    let idempotent = matches!(
+        method,
+        Method::Get | Method::Head | Method::Put | Method::Delete
+    );
+    let mut attempt = 0;
+
+    loop {
+        rate_limit_service::check(token)?;
+        match send_github_request(token, method.clone(), url, body.as_ref()).await {
+            Err(err) if idempotent && attempt < MAX_RETRIES => match backoff_delay(&err, attempt) {
+                Some(delay) => {
+                    Delay::from(delay).await;
+                    attempt += 1;
+                }
+                None => return Err(err),
+            },
+            result => return result,
+        }
+    }
+}
+
+fn backoff_delay(err: &GistDbError, attempt: u32) -> Option<Duration> {
+    let jitter = js_sys::Math::random() * BASE_BACKOFF_MS;
+    let exponential = BASE_BACKOFF_MS * 2f64.powi(attempt as i32);
+    let millis = match err {
+        GistDbError::RateLimited {
+            retry_after: Some(secs),
+        } if *secs <= MAX_RETRY_WAIT_SECS => (*secs * 1000) as f64 + jitter,
+        GistDbError::RateLimited { retry_after: None } => exponential + jitter,
+        GistDbError::Upstream { status, .. } if *status >= 500 => exponential + jitter,
+        _ => return None,
+    };
+    Some(Duration::from_millis(millis as u64))
+}
+
+async fn send_github_request(
+    token: &str,
+    method: Method,
+    url: &str,
+    body: Option<&Value>,
+) -> ApiResult<(Value, Headers)> {
     let mut init = RequestInit::new();
     let mut init = init.with_method(method);
     if let Some(body) = body {
-        let body_str = serde_json::to_string(&body)?;
+        let body_str = serde_json::to_string(body)?;
         init = init.with_body(Some(body_str.into()));
     }
 
-    let mut req = Request::new_with_init(url, &init)?;
+    let mut req = Request::new_with_init(url, init)?;
     req.headers_mut()?
         .set("Authorization", &format!("Bearer {}", token))?;
     req.headers_mut()?.set("User-Agent", "GistDB-API")?;
@@ -22,24 +83,136 @@ pub async fn github_request(
         .set("Accept", "application/vnd.github.v3+json")?;
     req.headers_mut()?.set("Content-Type", "application/json")?;
 
-    Fetch::Request(req).send().await?.json().await
+    let mut resp = Fetch::Request(req).send().await?;
+    rate_limit_service::record(token, resp.headers());
+
+    let status = resp.status_code();
+    let text = resp.text().await?;
+    let body = if text.trim().is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_str(&text).unwrap_or(Value::String(text))
+    };
+
+    if (200..300).contains(&status) {
+        Ok((body, resp.headers().clone()))
+    } else {
+        Err(github_error(status, resp.headers(), &body))
+    }
 }
 
-pub async fn get_gist_file(token: &str, gist_id: &str, filename: &str) -> Result<String> {
-    let url = format!("https://api.github.com/gists/{}", gist_id);
-    let response = github_request(token, Method::Get, &url, None).await?;
-    if let Some(files) = response["files"].as_object() {
-        if let Some(file) = files.get(filename) {
-            if let Some(content) = file["content"].as_str() {
-                return Ok(content.to_string());
-            }
+fn github_error(status: u16, headers: &Headers, body: &Value) -> GistDbError {
+    let message = body["message"]
+        .as_str()
+        .or_else(|| body.as_str())
+        .unwrap_or("GitHub API request failed")
+        .to_string();
+    let header = |name: &str| headers.get(name).ok().flatten();
+    let exhausted = header("x-ratelimit-remaining").as_deref() == Some("0");
+    let retry_after = header("retry-after")
+        .and_then(|v| v.parse().ok())
+        .or_else(|| {
+            header("x-ratelimit-reset")
+                .and_then(|v| v.parse().ok())
+                .filter(|_| exhausted)
+                .map(rate_limit_service::seconds_until_reset)
+        });
+
+    match status {
+        401 => GistDbError::Unauthorized(format!("GitHub rejected the token: {}", message)),
+        429 => GistDbError::RateLimited { retry_after },
+        403 if exhausted || retry_after.is_some() || message.contains("rate limit") => {
+            GistDbError::RateLimited { retry_after }
         }
+        403 => GistDbError::Forbidden(format!("GitHub denied access: {}", message)),
+        404 => GistDbError::NotFound(message),
+        409 => GistDbError::Conflict {
+            message,
+            details: None,
+        },
+        422 => GistDbError::Validation {
+            message,
+            details: Some(json!({
+                "github_status": status,
+                "errors": body.get("errors").cloned().unwrap_or(Value::Array(vec![])),
+            })),
+        },
+        _ => GistDbError::Upstream { status, message },
     }
+}
 
-    Err(worker::Error::RustError(format!(
-        "File '{}' not found in Gist '{}'",
-        filename, gist_id
-    )))
+pub fn gist_url(gist_id: &str) -> String {
+    format!("{}/gists/{}", GITHUB_API, gist_id)
+}
+
+fn database_not_found(gist_id: &str, err: GistDbError) -> GistDbError {
+    match err {
+        GistDbError::NotFound(_) => {
+            GistDbError::NotFound(format!("Database '{}' not found", gist_id))
+        }
+        err => err,
+    }
+}
+
+pub async fn get_gist(token: &str, gist_id: &str) -> ApiResult<Value> {
+    github_request(token, Method::Get, &gist_url(gist_id), None)
+        .await
+        .map_err(|e| database_not_found(gist_id, e))
+}
+
+pub async fn get_user(token: &str) -> ApiResult<Value> {
+    github_request(token, Method::Get, &format!("{}/user", GITHUB_API), None).await
+}
+
+pub async fn get_gist_revision(token: &str, gist_id: &str, sha: &str) -> ApiResult<Value> {
+    github_request(
+        token,
+        Method::Get,
+        &format!("{}/{}", gist_url(gist_id), sha),
+        None,
+    )
+    .await
+    .map_err(|e| match e {
+        GistDbError::NotFound(_) => GistDbError::NotFound(format!(
+            "Revision '{}' of database '{}' not found",
+            sha, gist_id
+        )),
+        err => err,
+    })
+}
+
+pub async fn list_gist_commits(
+    token: &str,
+    gist_id: &str,
+    page: u32,
+    per_page: u32,
+) -> ApiResult<Value> {
+    github_request(
+        token,
+        Method::Get,
+        &format!(
+            "{}/commits?per_page={}&page={}",
+            gist_url(gist_id),
+            per_page,
+            page
+        ),
+        None,
+    )
+    .await
+    .map_err(|e| database_not_found(gist_id, e))
+}
+
+pub async fn patch_gist(token: &str, gist_id: &str, body: Value) -> ApiResult<Value> {
+    github_request(token, Method::Patch, &gist_url(gist_id), Some(body))
+        .await
+        .map_err(|e| database_not_found(gist_id, e))
+}
+
+pub async fn delete_gist(token: &str, gist_id: &str) -> ApiResult<()> {
+    github_request(token, Method::Delete, &gist_url(gist_id), None)
+        .await
+        .map_err(|e| database_not_found(gist_id, e))?;
+    Ok(())
 }
 
 #[allow(dead_code)]
@@ -48,8 +221,7 @@ pub async fn update_gist_file(
     gist_id: &str,
     filename: &str,
     content: &str,
-) -> Result<()> {
-    let url = format!("https://api.github.com/gists/{}", gist_id);
+) -> ApiResult<()> {
     let body = json!({
         "files": {
             filename: {
@@ -57,7 +229,7 @@ pub async fn update_gist_file(
             }
         }
     });
-    github_request(token, Method::Patch, &url, Some(body)).await?;
+    patch_gist(token, gist_id, body).await?;
     Ok(())
 }
 
@@ -68,9 +240,8 @@ pub async fn get_gist_file_chunked(
     filename: &str,
     page: usize,
     chunk_size: usize,
-) -> Result<String> {
-    let url = format!("https://api.github.com/gists/{}", gist_id);
-    let response = github_request(token, Method::Get, &url, None).await?;
+) -> ApiResult<String> {
+    let response = get_gist(token, gist_id).await?;
     if let Some(files) = response["files"].as_object() {
         if let Some(file) = files.get(filename) {
             if let Some(content) = file["content"].as_str() {
@@ -86,7 +257,7 @@ pub async fn get_gist_file_chunked(
         }
     }
 
-    Err(worker::Error::RustError(format!(
+    Err(GistDbError::NotFound(format!(
         "File '{}' not found in Gist '{}'",
         filename, gist_id
     )))
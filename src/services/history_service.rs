@@ -0,0 +1,105 @@
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::services::database_service::{collection_file, validate_collection_name};
+use crate::services::github_service::{get_gist_revision, list_gist_commits};
+use crate::utils::cache;
+use crate::utils::diff::diff;
+use crate::utils::filter::Filter;
+use serde_json::{json, Map, Value};
+use worker::Cache;
+
+async fn collection_at(
+    token: &str,
+    gist_id: &str,
+    version: &str,
+    collection: &str,
+) -> ApiResult<Value> {
+    let cache_key = format!(
+        "https://gistdb.com/history/{}/{}/{}",
+        gist_id, version, collection
+    );
+
+    cache::cached_json(&Cache::default(), &cache_key, async {
+        let gist = get_gist_revision(token, gist_id, version).await?;
+        let Some(content) = gist["files"][collection_file(collection)]["content"].as_str() else {
+            return Ok(Value::Object(Map::new()));
+        };
+        serde_json::from_str(content).map_err(|e| {
+            GistDbError::Internal(format!(
+                "Collection '{}' contains invalid JSON at revision '{}': {}",
+                collection, version, e
+            ))
+        })
+    })
+    .await
+}
+
+pub async fn object_history(
+    token: &str,
+    gist_id: &str,
+    collection: &str,
+    object_id: &str,
+    page: u32,
+    per_page: u32,
+    row_filter: Option<&Filter>,
+) -> ApiResult<Value> {
+    validate_collection_name(collection)?;
+
+    let mut commits = list_gist_commits(token, gist_id, page, per_page)
+        .await?
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let page_len = commits.len();
+    // The revision just before this page is the baseline for its oldest entry.
+    if page_len == per_page as usize {
+        let baseline = list_gist_commits(token, gist_id, page * per_page + 1, 1).await?;
+        commits.extend(baseline.as_array().cloned().unwrap_or_default());
+    }
+    let has_more = commits.len() > page_len;
+
+    let mut versions = Vec::with_capacity(commits.len());
+    for commit in &commits {
+        let version = commit["version"].as_str().unwrap_or_default();
+        let object = collection_at(token, gist_id, version, collection)
+            .await?
+            .get_mut(object_id)
+            .map(Value::take)
+            .unwrap_or(Value::Null);
+        let visible = row_filter.is_none_or(|f| f.matches(&object));
+        versions.push(if visible { object } else { Value::Null });
+    }
+
+    let empty = Value::Object(Map::new());
+    let mut history = Vec::new();
+    for (i, commit) in commits.iter().enumerate().take(page_len) {
+        let new = &versions[i];
+        let old = versions.get(i + 1).unwrap_or(&Value::Null);
+        if old == new {
+            continue;
+        }
+        let change = match (old.is_null(), new.is_null()) {
+            (true, _) => "created",
+            (_, true) => "deleted",
+            _ => "updated",
+        };
+        history.push(json!({
+            "version": commit["version"],
+            "committed_at": commit["committed_at"],
+            "change": change,
+            "data": new,
+            "diff": diff(
+                if old.is_null() { &empty } else { old },
+                if new.is_null() { &empty } else { new },
+            ),
+        }));
+    }
+
+    Ok(json!({
+        "collection_name": collection,
+        "object_id": object_id,
+        "page": page,
+        "per_page": per_page,
+        "has_more": has_more,
+        "history": history,
+    }))
+}
@@ -18,15 +18,12 @@ impl SparseIndex {
 
     pub fn _create_index(&mut self, data: &Map<String, Value>, field: &str) -> Map<String, Value> {
         let mut index = Map::new();
-        let mut counter = 0;
-
-        for (id, obj) in data {
+        for (counter, (id, obj)) in data.iter().enumerate() {
             if counter % self.index_granularity == 0 {
                 if let Some(value) = obj.get(field) {
                     index.insert(id.clone(), value.clone());
                 }
             }
-            counter += 1;
         }
 
         let filter = self
@@ -0,0 +1,200 @@
+use crate::models::auth_models::{ApiKeyRecord, KeyScope, Principal, PrincipalKind};
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::services::github_service;
+use crate::utils::crypto::{decrypt, encrypt, random_bytes, sha256_hex, to_hex};
+use worker::kv::KvStore;
+use worker::Env;
+
+pub const API_KEY_PREFIX: &str = "gdb_";
+const KEYS_BINDING: &str = "GISTDB_KEYS";
+const ENCRYPTION_SECRET: &str = "GISTDB_ENCRYPTION_KEY";
+
+pub fn keys_store(env: &Env) -> ApiResult<KvStore> {
+    env.kv(KEYS_BINDING)
+        .map_err(|_| GistDbError::Internal(format!("KV namespace '{}' is not bound", KEYS_BINDING)))
+}
+
+pub fn encryption_secret(env: &Env) -> ApiResult<String> {
+    env.secret(ENCRYPTION_SECRET)
+        .map(|s| s.to_string())
+        .map_err(|_| {
+            GistDbError::Internal(format!("Secret '{}' is not configured", ENCRYPTION_SECRET))
+        })
+}
+
+/// Keys belong to the GitHub account rather than the token that minted them,
+/// so they can still be listed and revoked after that token is rotated.
+pub async fn owner_of(principal: &Principal) -> ApiResult<String> {
+    github_service::get_user(&principal.token).await?["id"]
+        .as_u64()
+        .map(|id| id.to_string())
+        .ok_or_else(|| GistDbError::Unauthorized("Could not identify the GitHub user".to_string()))
+}
+
+/// Keys created before ownership moved to the account were filed under a hash
+/// of the minting token.
+fn legacy_owner_of(principal: &Principal) -> String {
+    sha256_hex(&principal.token)
+}
+
+fn record_key(hash: &str) -> String {
+    format!("apikey:{}", hash)
+}
+
+fn owner_key(owner: &str, key_id: &str) -> String {
+    format!("owner:{}:{}", owner, key_id)
+}
+
+fn key_id_key(key_id: &str) -> String {
+    format!("keyid:{}", key_id)
+}
+
+pub async fn create_key(
+    env: &Env,
+    owner: &Principal,
+    name: String,
+    role: Option<String>,
+    scopes: Vec<KeyScope>,
+) -> ApiResult<(String, ApiKeyRecord)> {
+    if scopes.is_empty() {
+        return Err(GistDbError::BadRequest(
+            "An API key needs at least one scope".to_string(),
+        ));
+    }
+
+    let api_key = format!("{}{}", API_KEY_PREFIX, to_hex(&random_bytes::<32>()?));
+    let hash = sha256_hex(&api_key);
+    let record = ApiKeyRecord {
+        id: to_hex(&random_bytes::<8>()?),
+        name,
+        owner: owner_of(owner).await?,
+        encrypted_token: encrypt(&encryption_secret(env)?, &owner.token)?,
+        role,
+        scopes,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        revoked_at: None,
+    };
+
+    let store = keys_store(env)?;
+    store.put(&record_key(&hash), &record)?.execute().await?;
+    store
+        .put(&owner_key(&record.owner, &record.id), hash.as_str())?
+        .execute()
+        .await?;
+    store
+        .put(&key_id_key(&record.id), hash.as_str())?
+        .execute()
+        .await?;
+
+    Ok((api_key, record))
+}
+
+pub async fn list_keys(env: &Env, owner: &Principal) -> ApiResult<Vec<ApiKeyRecord>> {
+    let store = keys_store(env)?;
+    let mut records = list_owned(&store, &owner_key(&owner_of(owner).await?, "")).await?;
+    records.extend(list_owned(&store, &owner_key(&legacy_owner_of(owner), "")).await?);
+    Ok(records)
+}
+
+async fn list_owned(store: &KvStore, prefix: &str) -> ApiResult<Vec<ApiKeyRecord>> {
+    let mut records = Vec::new();
+    let mut cursor = None;
+
+    loop {
+        let mut list = store.list().prefix(prefix.to_string());
+        if let Some(cursor) = cursor {
+            list = list.cursor(cursor);
+        }
+        let page = list.execute().await?;
+
+        for key in page.keys {
+            if let Some(hash) = store.get(&key.name).text().await? {
+                if let Some(record) = store.get(&record_key(&hash)).json().await? {
+                    records.push(record);
+                }
+            }
+        }
+
+        if page.list_complete {
+            break;
+        }
+        cursor = page.cursor;
+    }
+
+    Ok(records)
+}
+
+pub async fn revoke_key(env: &Env, owner: &Principal, key_id: &str) -> ApiResult<ApiKeyRecord> {
+    let store = keys_store(env)?;
+    let hash = match store
+        .get(&owner_key(&owner_of(owner).await?, key_id))
+        .text()
+        .await?
+    {
+        Some(hash) => Some(hash),
+        None => {
+            store
+                .get(&owner_key(&legacy_owner_of(owner), key_id))
+                .text()
+                .await?
+        }
+    }
+    .ok_or_else(|| GistDbError::NotFound(format!("API key '{}' not found", key_id)))?;
+
+    let mut record: ApiKeyRecord = store
+        .get(&record_key(&hash))
+        .json()
+        .await?
+        .ok_or_else(|| GistDbError::NotFound(format!("API key '{}' not found", key_id)))?;
+
+    if record.revoked_at.is_none() {
+        record.revoked_at = Some(chrono::Utc::now().to_rfc3339());
+        store.put(&record_key(&hash), &record)?.execute().await?;
+    }
+    Ok(record)
+}
+
+/// `holder` carries the token the key was created with, which is enough to
+/// find keys from before the `keyid:` index existed.
+pub async fn is_key_active(env: &Env, holder: &Principal, key_id: &str) -> ApiResult<bool> {
+    let store = keys_store(env)?;
+    let hash = match store.get(&key_id_key(key_id)).text().await? {
+        Some(hash) => Some(hash),
+        None => {
+            store
+                .get(&owner_key(&legacy_owner_of(holder), key_id))
+                .text()
+                .await?
+        }
+    };
+    let Some(hash) = hash else {
+        return Ok(false);
+    };
+    let record: Option<ApiKeyRecord> = store.get(&record_key(&hash)).json().await?;
+    Ok(record.is_some_and(|record| record.revoked_at.is_none()))
+}
+
+pub async fn authenticate_key(env: &Env, api_key: &str) -> ApiResult<Principal> {
+    let record: ApiKeyRecord = keys_store(env)?
+        .get(&record_key(&sha256_hex(api_key)))
+        .json()
+        .await?
+        .ok_or_else(|| GistDbError::Unauthorized("Unknown API key".to_string()))?;
+
+    if record.revoked_at.is_some() {
+        return Err(GistDbError::Unauthorized(
+            "API key has been revoked".to_string(),
+        ));
+    }
+
+    Ok(Principal {
+        token: decrypt(&encryption_secret(env)?, &record.encrypted_token)?,
+        login: None,
+        scopes: None,
+        kind: PrincipalKind::ApiKey {
+            key_id: record.id,
+            role: record.role,
+            scopes: record.scopes,
+        },
+    })
+}
@@ -0,0 +1,98 @@
+use crate::models::auth_models::{AclAction, Operation, Permission, Principal};
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::models::request_models::Lookup;
+use crate::services::acl_service;
+use crate::services::database_service::Database;
+use crate::utils::filter::{get_path, set_path, Filter};
+use serde_json::{Map, Value};
+use worker::Env;
+
+#[derive(Debug)]
+pub struct LookupPlan {
+    lookup: Lookup,
+    filter: Option<Filter>,
+}
+
+pub fn parse_param(param: &str) -> ApiResult<Vec<Lookup>> {
+    param
+        .split(',')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let (field, from) = part.split_once(':').ok_or_else(|| {
+                GistDbError::BadRequest(format!(
+                    "Invalid lookup '{}': expected <field>:<collection>",
+                    part
+                ))
+            })?;
+            Ok(Lookup {
+                field: field.to_string(),
+                from: from.to_string(),
+                foreign_field: None,
+                as_field: None,
+            })
+        })
+        .collect()
+}
+
+pub async fn plan(
+    env: &Env,
+    principal: &Principal,
+    gist_id: &str,
+    lookups: Vec<Lookup>,
+) -> ApiResult<Vec<LookupPlan>> {
+    let mut plans = Vec::new();
+    for lookup in lookups {
+        let op = Operation {
+            database: gist_id.to_string(),
+            collection: Some(lookup.from.clone()),
+            permission: Permission::Read,
+            action: Some(AclAction::Read),
+        };
+        acl_service::authorize(env, principal, &op).await?;
+        let filter =
+            acl_service::load_row_filter(env, principal, gist_id, &lookup.from, AclAction::Read)
+                .await?;
+        plans.push(LookupPlan { lookup, filter });
+    }
+    Ok(plans)
+}
+
+fn find<'a>(target: &'a Map<String, Value>, plan: &LookupPlan, key: &Value) -> Option<&'a Value> {
+    let found = match &plan.lookup.foreign_field {
+        None => key.as_str().and_then(|id| target.get(id)),
+        Some(foreign) => target
+            .values()
+            .find(|object| get_path(object, foreign) == Some(key)),
+    };
+    found.filter(|object| plan.filter.as_ref().is_none_or(|f| f.matches(object)))
+}
+
+pub fn resolve<'a>(
+    db: &Database,
+    plans: &[LookupPlan],
+    objects: impl IntoIterator<Item = &'a mut Value>,
+) -> ApiResult<()> {
+    let targets = plans
+        .iter()
+        .map(|plan| db.collection(&plan.lookup.from))
+        .collect::<ApiResult<Vec<_>>>()?;
+
+    for object in objects {
+        for (plan, target) in plans.iter().zip(&targets) {
+            let Some(key) = get_path(object, &plan.lookup.field) else {
+                continue;
+            };
+            let resolved = match key {
+                Value::Array(keys) => Value::Array(
+                    keys.iter()
+                        .filter_map(|k| find(target, plan, k).cloned())
+                        .collect(),
+                ),
+                key => find(target, plan, key).cloned().unwrap_or(Value::Null),
+            };
+            let path = plan.lookup.as_field.as_ref().unwrap_or(&plan.lookup.field);
+            set_path(object, path, resolved);
+        }
+    }
+    Ok(())
+}
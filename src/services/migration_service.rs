@@ -0,0 +1,67 @@
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::models::manifest_models::{FORMAT_VERSION, MANIFEST_FILE};
+use crate::services::database_service::{database_description, Database, DESCRIPTION_PREFIX};
+use serde::Serialize;
+
+pub struct Migration {
+    pub from: u32,
+    pub description: &'static str,
+    pub apply: fn(&mut Database) -> ApiResult<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MigrationStep {
+    pub from: u32,
+    pub to: u32,
+    pub description: &'static str,
+    pub changes: Vec<String>,
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "Add the _gistdb.json manifest",
+    apply: add_manifest,
+}];
+
+fn add_manifest(db: &mut Database) -> ApiResult<Vec<String>> {
+    let mut changes = vec![format!(
+        "Write {} registering collections: {}",
+        MANIFEST_FILE,
+        db.collection_names().join(", ")
+    )];
+
+    if !db.description().starts_with(DESCRIPTION_PREFIX) {
+        let description = database_description(&db.manifest.name);
+        changes.push(format!("Set gist description to '{}'", description));
+        db.set_description(&description);
+    }
+
+    Ok(changes)
+}
+
+pub fn needs_migration(db: &Database) -> bool {
+    db.manifest.format_version < FORMAT_VERSION
+}
+
+pub fn migrate(db: &mut Database) -> ApiResult<Vec<MigrationStep>> {
+    let mut steps = Vec::new();
+
+    while needs_migration(db) {
+        let from = db.manifest.format_version;
+        let migration = MIGRATIONS.iter().find(|m| m.from == from).ok_or_else(|| {
+            GistDbError::Internal(format!("No migration from format version {}", from))
+        })?;
+
+        let changes = (migration.apply)(db)?;
+        db.manifest.format_version = from + 1;
+        db.mark_manifest_changed();
+        steps.push(MigrationStep {
+            from,
+            to: from + 1,
+            description: migration.description,
+            changes,
+        });
+    }
+
+    Ok(steps)
+}
@@ -1,2 +1,12 @@
+pub mod acl_service;
+pub mod auth_service;
+pub mod database_service;
 pub mod github_service;
-pub mod index_service;
\ No newline at end of file
+pub mod history_service;
+pub mod index_service;
+pub mod key_service;
+pub mod lookup_service;
+pub mod migration_service;
+pub mod rate_limit_service;
+pub mod share_service;
+pub mod sweep_service;
@@ -0,0 +1,83 @@
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::utils::crypto::sha256_hex;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use worker::Headers;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset: i64,
+}
+
+// Keyed by token hash, like the auth cache, so raw tokens never outlive the
+// request that carried them.
+thread_local! {
+    static RATE_LIMITS: RefCell<HashMap<String, RateLimit>> = RefCell::new(HashMap::new());
+}
+
+fn header_u64(headers: &Headers, name: &str) -> Option<u64> {
+    headers.get(name).ok().flatten()?.parse().ok()
+}
+
+pub fn record(token: &str, headers: &Headers) {
+    let (Some(limit), Some(remaining), Some(reset)) = (
+        header_u64(headers, "x-ratelimit-limit"),
+        header_u64(headers, "x-ratelimit-remaining"),
+        header_u64(headers, "x-ratelimit-reset"),
+    ) else {
+        return;
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    RATE_LIMITS.with(|limits| {
+        let mut limits = limits.borrow_mut();
+        limits.retain(|_, limit| limit.reset > now);
+        limits.insert(
+            sha256_hex(token),
+            RateLimit {
+                limit,
+                remaining,
+                reset: reset as i64,
+            },
+        );
+    });
+}
+
+pub fn current(token: &str) -> Option<RateLimit> {
+    let now = chrono::Utc::now().timestamp();
+    RATE_LIMITS.with(|limits| {
+        let mut limits = limits.borrow_mut();
+        let key = sha256_hex(token);
+        match limits.get(&key).copied() {
+            Some(limit) if limit.reset <= now => {
+                limits.remove(&key);
+                None
+            }
+            limit => limit,
+        }
+    })
+}
+
+pub fn seconds_until_reset(reset: i64) -> u64 {
+    (reset - chrono::Utc::now().timestamp()).max(1) as u64
+}
+
+pub fn check(token: &str) -> ApiResult<()> {
+    match current(token) {
+        Some(limit) if limit.remaining == 0 => Err(GistDbError::RateLimited {
+            retry_after: Some(seconds_until_reset(limit.reset)),
+        }),
+        _ => Ok(()),
+    }
+}
+
+pub fn apply_headers(token: &str, headers: &mut Headers) -> worker::Result<()> {
+    if let Some(limit) = current(token) {
+        headers.set("X-GitHub-RateLimit-Limit", &limit.limit.to_string())?;
+        headers.set("X-GitHub-RateLimit-Remaining", &limit.remaining.to_string())?;
+        headers.set("X-GitHub-RateLimit-Reset", &limit.reset.to_string())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use crate::models::auth_models::{Principal, PrincipalKind};
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::services::key_service::{self, encryption_secret};
+use crate::utils::crypto::{decode, decrypt, encode, encrypt, sign, verify_signature};
+use serde::{Deserialize, Serialize};
+use worker::Env;
+
+pub const SHARE_TOKEN_PREFIX: &str = "gdbs_";
+pub const DEFAULT_SHARE_TTL: u64 = 24 * 60 * 60;
+pub const MAX_SHARE_TTL: u64 = 30 * 24 * 60 * 60;
+const SIGNING_SECRET: &str = "SHARE_LINK_SECRET";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ShareClaims {
+    db: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    col: Option<String>,
+    exp: i64,
+    tok: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+}
+
+fn signing_secret(env: &Env) -> ApiResult<String> {
+    env.secret(SIGNING_SECRET)
+        .map(|s| s.to_string())
+        .map_err(|_| {
+            GistDbError::Internal(format!("Secret '{}' is not configured", SIGNING_SECRET))
+        })
+}
+
+pub fn create_share_token(
+    env: &Env,
+    issuer: &Principal,
+    database: &str,
+    collection: Option<&str>,
+    ttl: u64,
+) -> ApiResult<(String, i64)> {
+    if ttl == 0 || ttl > MAX_SHARE_TTL {
+        return Err(GistDbError::BadRequest(format!(
+            "expires_in must be between 1 and {} seconds",
+            MAX_SHARE_TTL
+        )));
+    }
+
+    let claims = ShareClaims {
+        db: database.to_string(),
+        col: collection.map(str::to_string),
+        exp: chrono::Utc::now().timestamp() + ttl as i64,
+        tok: encrypt(&encryption_secret(env)?, &issuer.token)?,
+        key: match &issuer.kind {
+            PrincipalKind::ApiKey { key_id, .. } => Some(key_id.clone()),
+            _ => None,
+        },
+    };
+    let payload = encode(&serde_json::to_vec(&claims)?);
+    let signature = sign(&signing_secret(env)?, &payload);
+
+    Ok((
+        format!("{}{}.{}", SHARE_TOKEN_PREFIX, payload, signature),
+        claims.exp,
+    ))
+}
+
+pub async fn authenticate_share(env: &Env, token: &str) -> ApiResult<Principal> {
+    let invalid = || GistDbError::Unauthorized("Invalid share token".to_string());
+    let (payload, signature) = token
+        .strip_prefix(SHARE_TOKEN_PREFIX)
+        .and_then(|rest| rest.split_once('.'))
+        .ok_or_else(invalid)?;
+
+    if !verify_signature(&signing_secret(env)?, payload, signature) {
+        return Err(invalid());
+    }
+
+    let claims: ShareClaims =
+        serde_json::from_slice(&decode(payload).map_err(|_| invalid())?).map_err(|_| invalid())?;
+    if claims.exp <= chrono::Utc::now().timestamp() {
+        return Err(GistDbError::Unauthorized(
+            "Share token has expired".to_string(),
+        ));
+    }
+
+    let principal = Principal {
+        token: decrypt(&encryption_secret(env)?, &claims.tok)?,
+        login: None,
+        scopes: None,
+        kind: PrincipalKind::ShareLink {
+            database: claims.db,
+            collection: claims.col,
+        },
+    };
+
+    // Links issued with an API key die with the key, not at their expiry.
+    if let Some(key_id) = &claims.key {
+        if !key_service::is_key_active(env, &principal, key_id).await? {
+            return Err(GistDbError::Unauthorized(
+                "Share token was issued by a revoked API key".to_string(),
+            ));
+        }
+    }
+    Ok(principal)
+}
@@ -0,0 +1,96 @@
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::services::database_service::Database;
+use crate::services::key_service::{encryption_secret, keys_store};
+use crate::utils::crypto::{decrypt, encrypt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use worker::{console_error, console_log, Env};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SweepRegistration {
+    pub gist_id: String,
+    pub encrypted_token: String,
+    pub registered_at: String,
+}
+
+fn sweep_key(gist_id: &str) -> String {
+    format!("sweep:{}", gist_id)
+}
+
+pub async fn register(env: &Env, token: &str, gist_id: &str) -> ApiResult<()> {
+    let registration = SweepRegistration {
+        gist_id: gist_id.to_string(),
+        encrypted_token: encrypt(&encryption_secret(env)?, token)?,
+        registered_at: chrono::Utc::now().to_rfc3339(),
+    };
+    keys_store(env)?
+        .put(&sweep_key(gist_id), &registration)?
+        .execute()
+        .await?;
+    Ok(())
+}
+
+pub async fn sweep_database(token: &str, gist_id: &str) -> ApiResult<Value> {
+    let mut db = Database::load(token, gist_id).await?;
+    let expired_objects = db.purge_expired_objects()?;
+    let purged_trash = db.purge_expired_trash();
+    if expired_objects + purged_trash > 0 {
+        db.commit(token).await?;
+    }
+    Ok(json!({
+        "gist_id": gist_id,
+        "expired_objects": expired_objects,
+        "purged_trash": purged_trash,
+    }))
+}
+
+pub async fn run(env: &Env) -> ApiResult<Vec<Value>> {
+    let store = keys_store(env)?;
+    let secret = encryption_secret(env)?;
+    let mut results = Vec::new();
+    let mut cursor = None;
+
+    loop {
+        let mut list = store.list().prefix(sweep_key(""));
+        if let Some(cursor) = cursor {
+            list = list.cursor(cursor);
+        }
+        let page = list.execute().await?;
+
+        for key in page.keys {
+            let registration = match store.get(&key.name).json::<SweepRegistration>().await {
+                Ok(Some(registration)) => registration,
+                Ok(None) => continue,
+                Err(err) => {
+                    console_error!("skipping {}: {}", key.name, err);
+                    continue;
+                }
+            };
+            let token = match decrypt(&secret, &registration.encrypted_token) {
+                Ok(token) => token,
+                Err(err) => {
+                    console_error!("skipping {}: {}", registration.gist_id, err);
+                    continue;
+                }
+            };
+            match sweep_database(&token, &registration.gist_id).await {
+                Ok(result) => {
+                    console_log!("swept {}: {}", registration.gist_id, result);
+                    results.push(result);
+                }
+                Err(GistDbError::NotFound(_) | GistDbError::Unauthorized(_)) => {
+                    console_log!("unregistering {} from sweeps", registration.gist_id);
+                    store.delete(&key.name).await?;
+                }
+                Err(err) => console_error!("sweeping {} failed: {}", registration.gist_id, err),
+            }
+        }
+
+        if page.list_complete {
+            break;
+        }
+        cursor = page.cursor;
+    }
+
+    Ok(results)
+}
@@ -1,18 +1,52 @@
+use crate::models::error_models::{ApiResult, GistDbError};
 use crate::models::response_models::ApiResponse;
 use serde_json::Value;
-use std::result::Result as StdResult;
-use worker::Response;
+use std::future::Future;
+use worker::{Response, Result};
 
 pub fn api_response(
     status: u16,
     data: Option<Value>,
     message: &str,
     error: &str,
-) -> StdResult<Response, worker::Error> {
-    Response::from_json(&ApiResponse {
+) -> ApiResult<Response> {
+    let response = Response::from_json(&ApiResponse {
         status,
         data,
         message: message.to_string(),
         error: error.to_string(),
-    })
+        code: None,
+        details: None,
+    })?;
+    Ok(response.with_status(status))
+}
+
+pub fn error_response(err: &GistDbError) -> Result<Response> {
+    let status = err.status();
+    let mut response = Response::from_json(&ApiResponse {
+        status,
+        data: None,
+        message: String::new(),
+        error: err.to_string(),
+        code: Some(err.code().to_string()),
+        details: err.details(),
+    })?
+    .with_status(status);
+
+    if let GistDbError::RateLimited {
+        retry_after: Some(secs),
+    } = err
+    {
+        response
+            .headers_mut()
+            .set("Retry-After", &secs.to_string())?;
+    }
+    Ok(response)
+}
+
+pub async fn handle(handler: impl Future<Output = ApiResult<Response>>) -> Result<Response> {
+    match handler.await {
+        Ok(response) => Ok(response),
+        Err(err) => error_response(&err),
+    }
 }
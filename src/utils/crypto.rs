@@ -0,0 +1,84 @@
+use crate::models::error_models::{ApiResult, GistDbError};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+const NONCE_LEN: usize = 12;
+
+pub fn random_bytes<const N: usize>() -> ApiResult<[u8; N]> {
+    let mut buf = [0u8; N];
+    getrandom::getrandom(&mut buf)
+        .map_err(|e| GistDbError::Internal(format!("Failed to generate random bytes: {}", e)))?;
+    Ok(buf)
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn sha256_hex(input: &str) -> String {
+    to_hex(&Sha256::digest(input.as_bytes()))
+}
+
+pub fn encode(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn decode(input: &str) -> ApiResult<Vec<u8>> {
+    URL_SAFE_NO_PAD
+        .decode(input)
+        .map_err(|_| GistDbError::BadRequest("Malformed base64 value".to_string()))
+}
+
+fn cipher(secret: &str) -> Aes256Gcm {
+    Aes256Gcm::new(&Sha256::digest(secret.as_bytes()))
+}
+
+pub fn encrypt(secret: &str, plaintext: &str) -> ApiResult<String> {
+    let nonce = random_bytes::<NONCE_LEN>()?;
+    let ciphertext = cipher(secret)
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|_| GistDbError::Internal("Failed to encrypt value".to_string()))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    Ok(encode(&sealed))
+}
+
+pub fn decrypt(secret: &str, sealed: &str) -> ApiResult<String> {
+    let sealed = decode(sealed)?;
+    if sealed.len() <= NONCE_LEN {
+        return Err(GistDbError::Internal(
+            "Encrypted value is truncated".to_string(),
+        ));
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let plaintext = cipher(secret)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| GistDbError::Internal("Failed to decrypt value".to_string()))?;
+    String::from_utf8(plaintext)
+        .map_err(|_| GistDbError::Internal("Decrypted value is not UTF-8".to_string()))
+}
+
+fn mac(secret: &str) -> Hmac<Sha256> {
+    <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length")
+}
+
+pub fn sign(secret: &str, message: &str) -> String {
+    let mut mac = mac(secret);
+    mac.update(message.as_bytes());
+    encode(&mac.finalize().into_bytes())
+}
+
+pub fn verify_signature(secret: &str, message: &str, signature: &str) -> bool {
+    let Ok(signature) = decode(signature) else {
+        return false;
+    };
+    let mut mac = mac(secret);
+    mac.update(message.as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
@@ -0,0 +1,50 @@
+use serde_json::{json, Value};
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn diff_into(path: &str, old: &Value, new: &Value, changes: &mut Vec<Value>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            for (key, old_value) in old_map {
+                match new_map.get(key) {
+                    Some(new_value) => diff_into(&join(path, key), old_value, new_value, changes),
+                    None => changes.push(json!({
+                        "op": "remove",
+                        "path": join(path, key),
+                        "old_value": old_value,
+                    })),
+                }
+            }
+            for (key, new_value) in new_map {
+                if !old_map.contains_key(key) {
+                    changes.push(json!({
+                        "op": "add",
+                        "path": join(path, key),
+                        "value": new_value,
+                    }));
+                }
+            }
+        }
+        _ if old != new => changes.push(json!({
+            "op": "replace",
+            "path": path,
+            "old_value": old,
+            "value": new,
+        })),
+        _ => {}
+    }
+}
+
+/// Field-level changes from `old` to `new`. Objects are compared key by key
+/// using dotted paths; arrays and scalars are replaced as a whole.
+pub fn diff(old: &Value, new: &Value) -> Vec<Value> {
+    let mut changes = Vec::new();
+    diff_into("", old, new, &mut changes);
+    changes
+}
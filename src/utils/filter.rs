@@ -0,0 +1,433 @@
+use crate::models::error_models::{ApiResult, GistDbError};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone)]
+pub enum Filter {
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+    Field {
+        path: String,
+        op: Comparison,
+        value: Value,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+    Nin,
+    Exists,
+    Contains,
+}
+
+impl Comparison {
+    fn parse(op: &str) -> ApiResult<Self> {
+        Ok(match op {
+            "$eq" => Comparison::Eq,
+            "$ne" => Comparison::Ne,
+            "$gt" => Comparison::Gt,
+            "$gte" => Comparison::Gte,
+            "$lt" => Comparison::Lt,
+            "$lte" => Comparison::Lte,
+            "$in" => Comparison::In,
+            "$nin" => Comparison::Nin,
+            "$exists" => Comparison::Exists,
+            "$contains" => Comparison::Contains,
+            _ => {
+                return Err(GistDbError::BadRequest(format!(
+                    "Unknown filter operator '{}'",
+                    op
+                )))
+            }
+        })
+    }
+}
+
+pub fn get_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |current, key| match current {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+fn array_index(items: &[Value], key: &str) -> Option<usize> {
+    key.parse::<usize>().ok().filter(|i| *i < items.len())
+}
+
+/// Sets `path`, creating objects along the way. Numeric segments index into
+/// existing arrays; returns false, changing nothing, when a segment meets an
+/// array it is not an index of.
+pub fn set_path(value: &mut Value, path: &str, new_value: Value) -> bool {
+    if !is_settable(value, path) {
+        return false;
+    }
+    let mut current = value;
+    let mut keys = path.split('.').peekable();
+    while let Some(key) = keys.next() {
+        let last = keys.peek().is_none();
+        if let Value::Array(items) = current {
+            let index = array_index(items, key).expect("checked by is_settable");
+            if last {
+                items[index] = new_value;
+                return true;
+            }
+            current = &mut items[index];
+            continue;
+        }
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        let Value::Object(map) = current else {
+            return false;
+        };
+        if last {
+            map.insert(key.to_string(), new_value);
+            return true;
+        }
+        current = map.entry(key).or_insert_with(|| Value::Object(Map::new()));
+    }
+    true
+}
+
+fn is_settable(value: &Value, path: &str) -> bool {
+    let mut current = Some(value);
+    for key in path.split('.') {
+        current = match current {
+            Some(Value::Array(items)) => match array_index(items, key) {
+                Some(index) => items.get(index),
+                None => return false,
+            },
+            Some(Value::Object(map)) => map.get(key),
+            _ => None,
+        };
+    }
+    true
+}
+
+/// Removes `path`. An array element is set to null rather than removed, so
+/// the indexes of its siblings stay put.
+pub fn remove_path(value: &mut Value, path: &str) {
+    let (parent, key) = match path.rsplit_once('.') {
+        Some((parent, key)) => (
+            parent
+                .split('.')
+                .try_fold(value, |current, key| match current {
+                    Value::Object(map) => map.get_mut(key),
+                    Value::Array(items) => array_index(items, key).map(|i| &mut items[i]),
+                    _ => None,
+                }),
+            key,
+        ),
+        None => (Some(value), path),
+    };
+    match parent {
+        Some(Value::Object(map)) => {
+            map.remove(key);
+        }
+        Some(Value::Array(items)) => {
+            if let Some(index) = array_index(items, key) {
+                items[index] = Value::Null;
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64()?.partial_cmp(&y.as_f64()?),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
+/// Numbers compare by value, so `1` equals `1.0`.
+pub fn values_equal(a: &Value, b: &Value) -> bool {
+    compare(a, b).map_or(a == b, |ord| ord == Ordering::Equal)
+}
+
+impl Filter {
+    pub fn parse(filter: &Value) -> ApiResult<Self> {
+        let map = filter
+            .as_object()
+            .ok_or_else(|| GistDbError::BadRequest("Filter must be a JSON object".to_string()))?;
+
+        let mut clauses = Vec::new();
+        for (key, value) in map {
+            clauses.push(match key.as_str() {
+                "$and" | "$or" => {
+                    let parts = value
+                        .as_array()
+                        .ok_or_else(|| {
+                            GistDbError::BadRequest(format!("'{}' expects an array", key))
+                        })?
+                        .iter()
+                        .map(Filter::parse)
+                        .collect::<ApiResult<Vec<_>>>()?;
+                    if key == "$and" {
+                        Filter::And(parts)
+                    } else {
+                        Filter::Or(parts)
+                    }
+                }
+                "$not" => Filter::Not(Box::new(Filter::parse(value)?)),
+                _ if key.starts_with('$') => {
+                    return Err(GistDbError::BadRequest(format!(
+                        "Unknown filter operator '{}'",
+                        key
+                    )))
+                }
+                path => Self::parse_field(path, value)?,
+            });
+        }
+
+        Ok(match clauses.len() {
+            1 => clauses.remove(0),
+            _ => Filter::And(clauses),
+        })
+    }
+
+    fn parse_field(path: &str, value: &Value) -> ApiResult<Self> {
+        let operators = match value {
+            Value::Object(map) if map.keys().all(|k| k.starts_with('$')) && !map.is_empty() => map,
+            _ => {
+                return Ok(Filter::Field {
+                    path: path.to_string(),
+                    op: Comparison::Eq,
+                    value: value.clone(),
+                })
+            }
+        };
+
+        let mut clauses = operators
+            .iter()
+            .map(|(op, operand)| {
+                Ok(Filter::Field {
+                    path: path.to_string(),
+                    op: Comparison::parse(op)?,
+                    value: operand.clone(),
+                })
+            })
+            .collect::<ApiResult<Vec<_>>>()?;
+
+        Ok(match clauses.len() {
+            1 => clauses.remove(0),
+            _ => Filter::And(clauses),
+        })
+    }
+
+    pub fn and(filters: impl IntoIterator<Item = Filter>) -> Option<Filter> {
+        let mut filters: Vec<Filter> = filters.into_iter().collect();
+        match filters.len() {
+            0 => None,
+            1 => filters.pop(),
+            _ => Some(Filter::And(filters)),
+        }
+    }
+
+    pub fn matches(&self, doc: &Value) -> bool {
+        match self {
+            Filter::And(parts) => parts.iter().all(|f| f.matches(doc)),
+            Filter::Or(parts) => parts.iter().any(|f| f.matches(doc)),
+            Filter::Not(inner) => !inner.matches(doc),
+            Filter::Field { path, op, value } => {
+                let actual = get_path(doc, path);
+                match op {
+                    Comparison::Exists => actual.is_some() == value.as_bool().unwrap_or(true),
+                    Comparison::Eq => actual.is_some_and(|a| values_equal(a, value)),
+                    Comparison::Ne => !actual.is_some_and(|a| values_equal(a, value)),
+                    Comparison::Gt | Comparison::Gte | Comparison::Lt | Comparison::Lte => actual
+                        .and_then(|a| compare(a, value))
+                        .is_some_and(|ord| match op {
+                            Comparison::Gt => ord == Ordering::Greater,
+                            Comparison::Gte => ord != Ordering::Less,
+                            Comparison::Lt => ord == Ordering::Less,
+                            _ => ord != Ordering::Greater,
+                        }),
+                    Comparison::In | Comparison::Nin => {
+                        let found = value.as_array().is_some_and(|options| {
+                            actual.is_some_and(|a| options.iter().any(|o| values_equal(a, o)))
+                        });
+                        found == (*op == Comparison::In)
+                    }
+                    Comparison::Contains => match actual {
+                        Some(Value::String(s)) => value.as_str().is_some_and(|v| s.contains(v)),
+                        Some(Value::Array(items)) => items.iter().any(|i| values_equal(i, value)),
+                        _ => false,
+                    },
+                }
+            }
+        }
+    }
+}
+
+pub fn bind_variables(filter: &Value, variables: &Map<String, Value>) -> Value {
+    match filter {
+        Value::String(s) => s
+            .strip_prefix('$')
+            .and_then(|name| variables.get(name))
+            .cloned()
+            .unwrap_or_else(|| filter.clone()),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| bind_variables(item, variables))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), bind_variables(v, variables)))
+                .collect(),
+        ),
+        _ => filter.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn matches(filter: Value, doc: Value) -> bool {
+        Filter::parse(&filter).unwrap().matches(&doc)
+    }
+
+    #[test]
+    fn ne_matches_missing_fields() {
+        assert!(matches(json!({ "status": { "$ne": "done" } }), json!({})));
+        assert!(matches(
+            json!({ "status": { "$ne": "done" } }),
+            json!({ "status": "open" })
+        ));
+        assert!(!matches(
+            json!({ "status": { "$ne": "done" } }),
+            json!({ "status": "done" })
+        ));
+        assert!(!matches(json!({ "n": { "$ne": 1 } }), json!({ "n": 1.0 })));
+    }
+
+    #[test]
+    fn exists_treats_null_as_present() {
+        let present = json!({ "a": { "$exists": true } });
+        let absent = json!({ "a": { "$exists": false } });
+        assert!(matches(present.clone(), json!({ "a": null })));
+        assert!(!matches(present, json!({ "b": 1 })));
+        assert!(matches(absent.clone(), json!({ "b": 1 })));
+        assert!(!matches(absent, json!({ "a": null })));
+    }
+
+    #[test]
+    fn null_only_equals_an_explicit_null() {
+        assert!(matches(json!({ "a": null }), json!({ "a": null })));
+        assert!(!matches(json!({ "a": null }), json!({})));
+        assert!(!matches(json!({ "a": null }), json!({ "a": 0 })));
+        assert!(matches(json!({ "a": { "$ne": null } }), json!({})));
+        assert!(!matches(
+            json!({ "a": { "$gte": null } }),
+            json!({ "a": null })
+        ));
+    }
+
+    #[test]
+    fn in_and_nin_over_missing_fields() {
+        assert!(matches(
+            json!({ "a": { "$in": [1, 2] } }),
+            json!({ "a": 2 })
+        ));
+        assert!(!matches(json!({ "a": { "$in": [1, 2] } }), json!({})));
+        assert!(matches(json!({ "a": { "$nin": [1, 2] } }), json!({})));
+        assert!(!matches(
+            json!({ "a": { "$nin": [1, 2] } }),
+            json!({ "a": 1 })
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_operators() {
+        assert!(Filter::parse(&json!({ "a": { "$regex": "x" } })).is_err());
+        assert!(Filter::parse(&json!({ "$nor": [] })).is_err());
+        assert!(Filter::parse(&json!([])).is_err());
+    }
+
+    #[test]
+    fn binds_variables_recursively() {
+        let variables = json!({ "principal.id": "key-1", "principal.login": "octocat" });
+        let variables = variables.as_object().unwrap();
+        let bound = bind_variables(
+            &json!({
+                "$or": [
+                    { "owner": "$principal.id" },
+                    { "editors": { "$contains": "$principal.login" } }
+                ],
+                "note": "$unknown",
+                "limit": 3
+            }),
+            variables,
+        );
+        assert_eq!(
+            bound,
+            json!({
+                "$or": [
+                    { "owner": "key-1" },
+                    { "editors": { "$contains": "octocat" } }
+                ],
+                "note": "$unknown",
+                "limit": 3
+            })
+        );
+    }
+
+    #[test]
+    fn bound_variables_are_not_reinterpreted() {
+        let variables = json!({ "principal.id": "$principal.login" });
+        let bound = bind_variables(
+            &json!({ "owner": "$principal.id" }),
+            variables.as_object().unwrap(),
+        );
+        assert_eq!(bound, json!({ "owner": "$principal.login" }));
+    }
+
+    #[test]
+    fn set_path_indexes_into_arrays() {
+        let mut doc = json!({ "items": [{ "qty": 1 }, { "qty": 5 }] });
+        assert!(set_path(&mut doc, "items.1.qty", json!(6)));
+        assert!(set_path(&mut doc, "items.0", json!("first")));
+        assert!(set_path(&mut doc, "meta.count", json!(2)));
+        assert_eq!(
+            doc,
+            json!({ "items": ["first", { "qty": 6 }], "meta": { "count": 2 } })
+        );
+    }
+
+    #[test]
+    fn set_path_refuses_keys_an_array_does_not_have() {
+        let mut doc = json!({ "items": [{ "qty": 1 }] });
+        assert!(!set_path(&mut doc, "items.1.qty", json!(2)));
+        assert!(!set_path(&mut doc, "items.qty", json!(2)));
+        assert_eq!(doc, json!({ "items": [{ "qty": 1 }] }));
+
+        let mut doc = json!({ "a": 1 });
+        assert!(set_path(&mut doc, "a.b", json!(2)));
+        assert_eq!(doc, json!({ "a": { "b": 2 } }));
+    }
+
+    #[test]
+    fn remove_path_reaches_into_arrays() {
+        let mut doc = json!({ "items": [{ "qty": 1, "note": "x" }, { "qty": 5 }] });
+        remove_path(&mut doc, "items.0.note");
+        remove_path(&mut doc, "items.1");
+        remove_path(&mut doc, "items.7.qty");
+        assert_eq!(doc, json!({ "items": [{ "qty": 1 }, null] }));
+    }
+}
@@ -1,3 +1,10 @@
 pub mod api_response;
 pub mod cache;
+pub mod crypto;
+pub mod diff;
+pub mod filter;
+pub mod records;
+pub mod request;
+pub mod schema;
 pub mod search;
+pub mod update;
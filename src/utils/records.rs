@@ -0,0 +1,332 @@
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::utils::filter::{get_path, set_path};
+use crate::utils::schema::{has_type, Schema};
+use serde_json::{Map, Value};
+
+pub const ID_FIELD: &str = "_id";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    Ndjson,
+    Csv,
+}
+
+impl RecordFormat {
+    pub fn parse(format: Option<&str>) -> ApiResult<Self> {
+        match format.unwrap_or("ndjson") {
+            "ndjson" => Ok(RecordFormat::Ndjson),
+            "csv" => Ok(RecordFormat::Csv),
+            other => Err(GistDbError::BadRequest(format!(
+                "Unknown format '{}': use 'ndjson' or 'csv'",
+                other
+            ))),
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            RecordFormat::Ndjson => "application/x-ndjson",
+            RecordFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            RecordFormat::Ndjson => "ndjson",
+            RecordFormat::Csv => "csv",
+        }
+    }
+}
+
+fn flatten_into(prefix: &str, value: &Value, paths: &mut Vec<String>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, nested) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_into(&path, nested, paths);
+            }
+        }
+        _ => {
+            if !paths.iter().any(|p| p == prefix) {
+                paths.push(prefix.to_string());
+            }
+        }
+    }
+}
+
+/// Leaf paths of all objects in first-seen order. Nested objects become
+/// dotted paths; arrays stay a single column.
+pub fn columns<'a>(objects: impl IntoIterator<Item = &'a Value>) -> Vec<String> {
+    let mut paths = Vec::new();
+    for object in objects {
+        if let Value::Object(map) = object {
+            for (key, value) in map {
+                flatten_into(key, value, &mut paths);
+            }
+        }
+    }
+    paths.retain(|p| p != ID_FIELD);
+    paths
+}
+
+pub fn ndjson_line(id: &str, object: &Value) -> ApiResult<String> {
+    let mut record = object.as_object().cloned().unwrap_or_default();
+    record.insert(ID_FIELD.to_string(), Value::String(id.to_string()));
+    Ok(format!("{}\n", serde_json::to_string(&record)?))
+}
+
+fn csv_cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+pub fn csv_chunk(
+    columns: &[String],
+    rows: &[(String, Value)],
+    with_header: bool,
+) -> ApiResult<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let csv_error = |e: csv::Error| GistDbError::Internal(format!("Failed to write CSV: {}", e));
+    if with_header {
+        writer
+            .write_record(std::iter::once(ID_FIELD).chain(columns.iter().map(String::as_str)))
+            .map_err(csv_error)?;
+    }
+    for (id, object) in rows {
+        let cells = columns.iter().map(|path| csv_cell(get_path(object, path)));
+        writer
+            .write_record(std::iter::once(id.clone()).chain(cells))
+            .map_err(csv_error)?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| GistDbError::Internal(format!("Failed to write CSV: {}", e)))
+}
+
+fn take_id(row: usize, mut object: Value) -> ApiResult<(Option<String>, Value)> {
+    let id = match object.as_object_mut().and_then(|map| map.remove(ID_FIELD)) {
+        None | Some(Value::Null) => None,
+        Some(Value::String(id)) if !id.is_empty() => Some(id),
+        Some(_) => {
+            return Err(GistDbError::BadRequest(format!(
+                "Row {}: '{}' must be a non-empty string",
+                row, ID_FIELD
+            )))
+        }
+    };
+    Ok((id, object))
+}
+
+pub fn parse_ndjson(text: &str) -> ApiResult<Vec<(Option<String>, Value)>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let object: Value = serde_json::from_str(line).map_err(|e| {
+                GistDbError::BadRequest(format!("Row {}: invalid JSON: {}", i + 1, e))
+            })?;
+            if !object.is_object() {
+                return Err(GistDbError::BadRequest(format!(
+                    "Row {}: expected a JSON object",
+                    i + 1
+                )));
+            }
+            take_id(i + 1, object)
+        })
+        .collect()
+}
+
+/// A cell becomes a JSON number, boolean, array or object only when its
+/// column's declared `types` allow that type, or, for undeclared columns, when
+/// `infer` is set. Anything else is a string. Empty cells leave the field out.
+fn parse_cell(cell: &str, types: Option<&[String]>, infer: bool) -> Option<Value> {
+    if cell.is_empty() {
+        return None;
+    }
+    let wants_json = match types {
+        Some(types) => types.iter().any(|t| t != "string"),
+        None => infer,
+    };
+    if wants_json {
+        if let Ok(
+            value @ (Value::Number(_) | Value::Bool(_) | Value::Array(_) | Value::Object(_)),
+        ) = serde_json::from_str::<Value>(cell)
+        {
+            if types.is_none_or(|types| types.iter().any(|t| has_type(&value, t))) {
+                return Some(value);
+            }
+        }
+    }
+    Some(Value::String(cell.to_string()))
+}
+
+pub fn parse_csv(
+    text: &str,
+    schema: Option<&Schema>,
+    infer_types: bool,
+) -> ApiResult<Vec<(Option<String>, Value)>> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let header: Vec<String> = reader
+        .headers()
+        .map_err(|e| GistDbError::BadRequest(format!("Invalid CSV header: {}", e)))?
+        .iter()
+        .map(str::to_string)
+        .collect();
+    if let Some(path) = header
+        .iter()
+        .find(|p| p.is_empty() || p.split('.').any(str::is_empty))
+    {
+        return Err(GistDbError::BadRequest(format!(
+            "Invalid column path '{}'",
+            path
+        )));
+    }
+
+    reader
+        .records()
+        .enumerate()
+        .map(|(i, record)| {
+            let record =
+                record.map_err(|e| GistDbError::BadRequest(format!("Row {}: {}", i + 1, e)))?;
+            let mut object = Value::Object(Map::new());
+            for (path, cell) in header.iter().zip(record.iter()) {
+                let value = match path.as_str() {
+                    ID_FIELD if !cell.is_empty() => Some(Value::String(cell.to_string())),
+                    _ => parse_cell(
+                        cell,
+                        schema.and_then(|s| s.declared_types(path)),
+                        infer_types,
+                    ),
+                };
+                if let Some(value) = value {
+                    set_path(&mut object, path, value);
+                }
+            }
+            take_id(i + 1, object)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rows() -> Vec<(String, Value)> {
+        vec![
+            (
+                "a".to_string(),
+                json!({ "name": "Ann", "zip": "01234", "address": { "city": "Oslo" }, "tags": ["x", "y"] }),
+            ),
+            (
+                "b".to_string(),
+                json!({ "name": "Bob", "zip": "12345", "age": 41, "active": true }),
+            ),
+        ]
+    }
+
+    fn csv_text(rows: &[(String, Value)]) -> String {
+        let columns = columns(rows.iter().map(|(_, object)| object));
+        String::from_utf8(csv_chunk(&columns, rows, true).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn columns_flatten_nested_objects_in_first_seen_order() {
+        let objects = [
+            json!({ "_id": "x", "name": "a", "address": { "city": "Oslo", "geo": { "lat": 1 } } }),
+            json!({ "tags": ["x"], "address": { "zip": "1" }, "empty": {} }),
+        ];
+        assert_eq!(
+            columns(&objects),
+            [
+                "address.city",
+                "address.geo.lat",
+                "name",
+                "address.zip",
+                "empty",
+                "tags"
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_round_trip_follows_the_schema() {
+        let schema = Schema::parse(&json!({
+            "properties": {
+                "name": { "type": "string" },
+                "zip": { "type": "string" },
+                "age": { "type": "integer" },
+                "active": { "type": "boolean" },
+                "tags": { "type": "array" },
+                "address": { "properties": { "city": { "type": "string" } } },
+            }
+        }))
+        .unwrap();
+
+        let parsed = parse_csv(&csv_text(&rows()), Some(&schema), false).unwrap();
+        let expected: Vec<(Option<String>, Value)> = rows()
+            .into_iter()
+            .map(|(id, object)| (Some(id), object))
+            .collect();
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn undeclared_csv_columns_stay_strings_unless_inferred() {
+        let text = "_id,zip,flag,tags,note\na,12345,true,\"[1,2]\",\n";
+        let (_, object) = parse_csv(text, None, false).unwrap().remove(0);
+        assert_eq!(
+            object,
+            json!({ "zip": "12345", "flag": "true", "tags": "[1,2]" })
+        );
+
+        let (_, object) = parse_csv(text, None, true).unwrap().remove(0);
+        assert_eq!(
+            object,
+            json!({ "zip": 12345, "flag": true, "tags": [1, 2] })
+        );
+    }
+
+    #[test]
+    fn declared_types_reject_cells_of_another_type() {
+        let schema =
+            Schema::parse(&json!({ "properties": { "age": { "type": "integer" } } })).unwrap();
+        let (_, object) = parse_csv("age\n4.5\n", Some(&schema), false)
+            .unwrap()
+            .remove(0);
+        assert_eq!(object, json!({ "age": "4.5" }));
+    }
+
+    #[test]
+    fn ndjson_round_trip_keeps_types_and_ids() {
+        let text: String = rows()
+            .iter()
+            .map(|(id, object)| ndjson_line(id, object).unwrap())
+            .collect();
+        let parsed = parse_ndjson(&format!("{}\n{}", text, "{\"_id\": null, \"n\": 1}")).unwrap();
+        assert_eq!(parsed[0], (Some("a".to_string()), rows()[0].1.clone()));
+        assert_eq!(parsed[1], (Some("b".to_string()), rows()[1].1.clone()));
+        assert_eq!(parsed[2], (None, json!({ "n": 1 })));
+    }
+
+    #[test]
+    fn duplicate_ids_are_parsed_for_the_import_to_reject() {
+        let parsed = parse_csv("_id,n\na,1\na,2\n", None, true).unwrap();
+        assert_eq!(parsed[0].0.as_deref(), Some("a"));
+        assert_eq!(parsed[1].0.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn rejects_malformed_records() {
+        assert!(parse_ndjson("[1]\n").is_err());
+        assert!(parse_ndjson("{\"_id\": 3}\n").is_err());
+        assert!(parse_csv("a..b\n1\n", None, false).is_err());
+    }
+}
@@ -0,0 +1,9 @@
+use crate::models::error_models::{ApiResult, GistDbError};
+use serde::de::DeserializeOwned;
+use worker::Request;
+
+pub async fn parse_body<T: DeserializeOwned>(req: &mut Request) -> ApiResult<T> {
+    req.json()
+        .await
+        .map_err(|e| GistDbError::BadRequest(format!("Invalid request body: {}", e)))
+}
@@ -0,0 +1,497 @@
+use crate::models::error_models::{ApiResult, GistDbError};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+const UNSUPPORTED_KEYWORDS: [&str; 12] = [
+    "$ref",
+    "$dynamicRef",
+    "if",
+    "then",
+    "else",
+    "patternProperties",
+    "propertyNames",
+    "dependentRequired",
+    "dependentSchemas",
+    "contains",
+    "unevaluatedProperties",
+    "unevaluatedItems",
+];
+
+#[derive(Debug, Clone)]
+pub enum Schema {
+    Bool(bool),
+    Object(Box<SchemaObject>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SchemaObject {
+    types: Option<Vec<String>>,
+    enum_values: Option<Vec<Value>>,
+    const_value: Option<Value>,
+    properties: Vec<(String, Schema)>,
+    required: Vec<String>,
+    additional_properties: Option<Schema>,
+    min_properties: Option<usize>,
+    max_properties: Option<usize>,
+    prefix_items: Vec<Schema>,
+    items: Option<Schema>,
+    min_items: Option<usize>,
+    max_items: Option<usize>,
+    unique_items: bool,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    pattern: Option<Regex>,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    exclusive_minimum: Option<f64>,
+    exclusive_maximum: Option<f64>,
+    multiple_of: Option<f64>,
+    all_of: Vec<Schema>,
+    any_of: Vec<Schema>,
+    one_of: Vec<Schema>,
+    not: Option<Schema>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Violation {
+    pub path: String,
+    pub message: String,
+}
+
+fn invalid(keyword: &str, expected: &str) -> GistDbError {
+    GistDbError::BadRequest(format!("Schema keyword '{}' must be {}", keyword, expected))
+}
+
+fn size(map: &Map<String, Value>, keyword: &str) -> ApiResult<Option<usize>> {
+    map.get(keyword)
+        .map(|v| {
+            v.as_u64()
+                .map(|n| n as usize)
+                .ok_or_else(|| invalid(keyword, "a non-negative integer"))
+        })
+        .transpose()
+}
+
+fn number(map: &Map<String, Value>, keyword: &str) -> ApiResult<Option<f64>> {
+    map.get(keyword)
+        .map(|v| v.as_f64().ok_or_else(|| invalid(keyword, "a number")))
+        .transpose()
+}
+
+fn subschemas(map: &Map<String, Value>, keyword: &str) -> ApiResult<Vec<Schema>> {
+    match map.get(keyword) {
+        None => Ok(Vec::new()),
+        Some(Value::Array(items)) if !items.is_empty() => items.iter().map(Schema::parse).collect(),
+        Some(_) => Err(invalid(keyword, "a non-empty array of schemas")),
+    }
+}
+
+fn subschema(map: &Map<String, Value>, keyword: &str) -> ApiResult<Option<Schema>> {
+    map.get(keyword).map(Schema::parse).transpose()
+}
+
+fn pointer(path: &str, segment: &str) -> String {
+    let segment = segment.replace('~', "~0").replace('/', "~1");
+    format!("{}/{}", path.trim_end_matches('/'), segment)
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+pub fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+impl Schema {
+    pub fn parse(schema: &Value) -> ApiResult<Self> {
+        let map = match schema {
+            Value::Bool(b) => return Ok(Schema::Bool(*b)),
+            Value::Object(map) => map,
+            _ => {
+                return Err(GistDbError::BadRequest(
+                    "Schema must be a JSON object or boolean".to_string(),
+                ))
+            }
+        };
+
+        if let Some(keyword) = UNSUPPORTED_KEYWORDS.iter().find(|k| map.contains_key(**k)) {
+            return Err(GistDbError::BadRequest(format!(
+                "Schema keyword '{}' is not supported",
+                keyword
+            )));
+        }
+
+        let types = match map.get("type") {
+            None => None,
+            Some(Value::String(t)) => Some(vec![t.clone()]),
+            Some(Value::Array(ts)) => Some(
+                ts.iter()
+                    .map(|t| t.as_str().map(str::to_string))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| invalid("type", "a string or array of strings"))?,
+            ),
+            Some(_) => return Err(invalid("type", "a string or array of strings")),
+        };
+        if let Some(t) = types.iter().flatten().find(|t| {
+            !matches!(
+                t.as_str(),
+                "null" | "boolean" | "integer" | "number" | "string" | "array" | "object"
+            )
+        }) {
+            return Err(GistDbError::BadRequest(format!(
+                "Unknown schema type '{}'",
+                t
+            )));
+        }
+
+        let properties = match map.get("properties") {
+            None => Vec::new(),
+            Some(Value::Object(props)) => props
+                .iter()
+                .map(|(name, s)| Ok((name.clone(), Schema::parse(s)?)))
+                .collect::<ApiResult<Vec<_>>>()?,
+            Some(_) => return Err(invalid("properties", "an object")),
+        };
+        let required = match map.get("required") {
+            None => Vec::new(),
+            Some(Value::Array(names)) => names
+                .iter()
+                .map(|n| n.as_str().map(str::to_string))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| invalid("required", "an array of strings"))?,
+            Some(_) => return Err(invalid("required", "an array of strings")),
+        };
+        let enum_values = match map.get("enum") {
+            None => None,
+            Some(Value::Array(values)) => Some(values.clone()),
+            Some(_) => return Err(invalid("enum", "an array")),
+        };
+        let pattern = match map.get("pattern") {
+            None => None,
+            Some(Value::String(p)) => Some(Regex::new(p).map_err(|e| {
+                GistDbError::BadRequest(format!("Schema pattern '{}' is invalid: {}", p, e))
+            })?),
+            Some(_) => return Err(invalid("pattern", "a string")),
+        };
+        let unique_items = match map.get("uniqueItems") {
+            None => false,
+            Some(Value::Bool(b)) => *b,
+            Some(_) => return Err(invalid("uniqueItems", "a boolean")),
+        };
+
+        Ok(Schema::Object(Box::new(SchemaObject {
+            types,
+            enum_values,
+            const_value: map.get("const").cloned(),
+            properties,
+            required,
+            additional_properties: subschema(map, "additionalProperties")?,
+            min_properties: size(map, "minProperties")?,
+            max_properties: size(map, "maxProperties")?,
+            prefix_items: subschemas(map, "prefixItems")?,
+            items: subschema(map, "items")?,
+            min_items: size(map, "minItems")?,
+            max_items: size(map, "maxItems")?,
+            unique_items,
+            min_length: size(map, "minLength")?,
+            max_length: size(map, "maxLength")?,
+            pattern,
+            minimum: number(map, "minimum")?,
+            maximum: number(map, "maximum")?,
+            exclusive_minimum: number(map, "exclusiveMinimum")?,
+            exclusive_maximum: number(map, "exclusiveMaximum")?,
+            multiple_of: number(map, "multipleOf")?,
+            all_of: subschemas(map, "allOf")?,
+            any_of: subschemas(map, "anyOf")?,
+            one_of: subschemas(map, "oneOf")?,
+            not: subschema(map, "not")?,
+        })))
+    }
+
+    /// The `type` declared for a dotted property path, following
+    /// `properties` and `additionalProperties`.
+    pub fn declared_types(&self, path: &str) -> Option<&[String]> {
+        let mut schema = self;
+        for key in path.split('.') {
+            let Schema::Object(object) = schema else {
+                return None;
+            };
+            schema = object
+                .properties
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, s)| s)
+                .or(object.additional_properties.as_ref())?;
+        }
+        match schema {
+            Schema::Object(object) => object.types.as_deref(),
+            Schema::Bool(_) => None,
+        }
+    }
+
+    pub fn validate(&self, value: &Value) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check(value, "/", &mut violations);
+        violations
+    }
+
+    fn is_valid(&self, value: &Value) -> bool {
+        self.validate(value).is_empty()
+    }
+
+    fn check(&self, value: &Value, path: &str, out: &mut Vec<Violation>) {
+        let schema = match self {
+            Schema::Bool(true) => return,
+            Schema::Bool(false) => {
+                out.push(Violation {
+                    path: path.to_string(),
+                    message: "No value is allowed here".to_string(),
+                });
+                return;
+            }
+            Schema::Object(schema) => schema,
+        };
+        let mut fail = |message: String| {
+            out.push(Violation {
+                path: path.to_string(),
+                message,
+            })
+        };
+
+        if let Some(types) = &schema.types {
+            if !types.iter().any(|t| has_type(value, t)) {
+                fail(format!(
+                    "Expected {}, found {}",
+                    types.join(" or "),
+                    type_name(value)
+                ));
+                return;
+            }
+        }
+        if let Some(options) = &schema.enum_values {
+            if !options.contains(value) {
+                fail(format!(
+                    "Value must be one of {}",
+                    Value::Array(options.clone())
+                ));
+            }
+        }
+        if let Some(expected) = &schema.const_value {
+            if value != expected {
+                fail(format!("Value must be {}", expected));
+            }
+        }
+
+        match value {
+            Value::String(s) => {
+                let length = s.chars().count();
+                if let Some(min) = schema.min_length.filter(|min| length < *min) {
+                    fail(format!("String is shorter than {} characters", min));
+                }
+                if let Some(max) = schema.max_length.filter(|max| length > *max) {
+                    fail(format!("String is longer than {} characters", max));
+                }
+                if let Some(pattern) = schema.pattern.as_ref().filter(|p| !p.is_match(s)) {
+                    fail(format!("String does not match pattern '{}'", pattern));
+                }
+            }
+            Value::Number(n) => {
+                let n = n.as_f64().unwrap_or_default();
+                if let Some(min) = schema.minimum.filter(|min| n < *min) {
+                    fail(format!("Value is less than the minimum {}", min));
+                }
+                if let Some(max) = schema.maximum.filter(|max| n > *max) {
+                    fail(format!("Value is greater than the maximum {}", max));
+                }
+                if let Some(min) = schema.exclusive_minimum.filter(|min| n <= *min) {
+                    fail(format!("Value must be greater than {}", min));
+                }
+                if let Some(max) = schema.exclusive_maximum.filter(|max| n >= *max) {
+                    fail(format!("Value must be less than {}", max));
+                }
+                if let Some(step) = schema
+                    .multiple_of
+                    .filter(|step| *step > 0.0 && (n / step).fract() != 0.0)
+                {
+                    fail(format!("Value is not a multiple of {}", step));
+                }
+            }
+            Value::Array(items) => {
+                if let Some(min) = schema.min_items.filter(|min| items.len() < *min) {
+                    fail(format!("Array has fewer than {} items", min));
+                }
+                if let Some(max) = schema.max_items.filter(|max| items.len() > *max) {
+                    fail(format!("Array has more than {} items", max));
+                }
+                if schema.unique_items
+                    && items
+                        .iter()
+                        .enumerate()
+                        .any(|(i, item)| items[..i].contains(item))
+                {
+                    fail("Array items must be unique".to_string());
+                }
+            }
+            Value::Object(map) => {
+                for name in schema.required.iter().filter(|n| !map.contains_key(*n)) {
+                    fail(format!("Missing required property '{}'", name));
+                }
+                if let Some(min) = schema.min_properties.filter(|min| map.len() < *min) {
+                    fail(format!("Object has fewer than {} properties", min));
+                }
+                if let Some(max) = schema.max_properties.filter(|max| map.len() > *max) {
+                    fail(format!("Object has more than {} properties", max));
+                }
+            }
+            _ => {}
+        }
+
+        if !schema.all_of.is_empty() && !schema.all_of.iter().all(|s| s.is_valid(value)) {
+            fail("Value does not match all schemas in 'allOf'".to_string());
+        }
+        if !schema.any_of.is_empty() && !schema.any_of.iter().any(|s| s.is_valid(value)) {
+            fail("Value does not match any schema in 'anyOf'".to_string());
+        }
+        if !schema.one_of.is_empty()
+            && schema.one_of.iter().filter(|s| s.is_valid(value)).count() != 1
+        {
+            fail("Value must match exactly one schema in 'oneOf'".to_string());
+        }
+        if schema.not.as_ref().is_some_and(|s| s.is_valid(value)) {
+            fail("Value must not match the schema in 'not'".to_string());
+        }
+
+        match value {
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    let item_schema = schema.prefix_items.get(i).or(schema.items.as_ref());
+                    if let Some(item_schema) = item_schema {
+                        item_schema.check(item, &pointer(path, &i.to_string()), out);
+                    }
+                }
+            }
+            Value::Object(map) => {
+                for (name, property) in map {
+                    let property_schema = schema
+                        .properties
+                        .iter()
+                        .find(|(n, _)| n == name)
+                        .map(|(_, s)| s)
+                        .or(schema.additional_properties.as_ref());
+                    if let Some(property_schema) = property_schema {
+                        property_schema.check(property, &pointer(path, name), out);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn violations(schema: Value, value: Value) -> Vec<(String, String)> {
+        Schema::parse(&schema)
+            .unwrap()
+            .validate(&value)
+            .into_iter()
+            .map(|v| (v.path, v.message))
+            .collect()
+    }
+
+    #[test]
+    fn additional_properties_false_rejects_unknown_fields() {
+        let schema = json!({
+            "type": "object",
+            "properties": { "name": { "type": "string" } },
+            "additionalProperties": false
+        });
+        assert!(violations(schema.clone(), json!({ "name": "a" })).is_empty());
+        assert_eq!(
+            violations(schema, json!({ "name": "a", "age": 3 })),
+            vec![("/age".to_string(), "No value is allowed here".to_string())]
+        );
+    }
+
+    #[test]
+    fn additional_properties_schema_only_applies_to_unlisted_fields() {
+        let schema = json!({
+            "properties": { "name": { "type": "string" } },
+            "additionalProperties": { "type": "integer" }
+        });
+        assert!(violations(schema.clone(), json!({ "name": "a", "age": 3 })).is_empty());
+        assert_eq!(
+            violations(schema, json!({ "name": "a", "age": "3" })),
+            vec![(
+                "/age".to_string(),
+                "Expected integer, found string".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn one_of_requires_exactly_one_match() {
+        let schema = json!({ "oneOf": [{ "type": "integer" }, { "minimum": 10 }] });
+        assert!(violations(schema.clone(), json!(3)).is_empty());
+        assert!(violations(schema.clone(), json!(10.5)).is_empty());
+        assert_eq!(violations(schema.clone(), json!(12)).len(), 1);
+        assert_eq!(violations(schema, json!(2.5)).len(), 1);
+    }
+
+    #[test]
+    fn integer_accepts_whole_floats_only() {
+        let schema = json!({ "type": "integer" });
+        assert!(violations(schema.clone(), json!(3)).is_empty());
+        assert!(violations(schema.clone(), json!(3.0)).is_empty());
+        assert!(violations(schema.clone(), json!(-7)).is_empty());
+        assert_eq!(
+            violations(schema.clone(), json!(3.5)),
+            vec![(
+                "/".to_string(),
+                "Expected integer, found number".to_string()
+            )]
+        );
+        assert_eq!(violations(schema, json!("3")).len(), 1);
+        assert!(violations(json!({ "type": "number" }), json!(3)).is_empty());
+    }
+
+    #[test]
+    fn nested_paths_use_json_pointers() {
+        let schema = json!({
+            "properties": { "a/b": { "items": { "type": "string" } } }
+        });
+        assert_eq!(
+            violations(schema, json!({ "a/b": ["x", 1] })),
+            vec![(
+                "/a~1b/1".to_string(),
+                "Expected string, found integer".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_and_malformed_keywords() {
+        assert!(Schema::parse(&json!({ "$ref": "#/defs/a" })).is_err());
+        assert!(Schema::parse(&json!({ "type": "int" })).is_err());
+        assert!(Schema::parse(&json!({ "oneOf": [] })).is_err());
+        assert!(Schema::parse(&json!({ "minLength": -1 })).is_err());
+        assert!(Schema::parse(&json!("object")).is_err());
+    }
+}
@@ -1,13 +1,18 @@
-use crate::models::request_models::SearchRequest;
+use crate::utils::filter::Filter;
 use serde_json::Value;
 use worker::Result;
-use worker::*;
 
-pub fn search_json(data: &Value, query: &str, field: Option<&str>) -> Result<Vec<String>> {
+pub fn search_json(
+    data: &Value,
+    query: Option<&str>,
+    field: Option<&str>,
+    filter: Option<&Filter>,
+) -> Result<Vec<String>> {
     let mut results = Vec::new();
     if let Some(obj) = data.as_object() {
         for (id, value) in obj {
-            if matches_query(value, query, field) {
+            let query_matches = query.is_none_or(|q| matches_query(value, q, field));
+            if query_matches && filter.is_none_or(|f| f.matches(value)) {
                 results.push(id.clone());
             }
         }
@@ -35,16 +40,3 @@ fn json_contains(value: &Value, query: &str) -> bool {
         _ => false,
     }
 }
-
-pub fn get_auth_token(req: &Request) -> Result<String> {
-    req.headers()
-        .get("Authorization")?
-        .ok_or_else(|| worker::Error::RustError("Authorization header missing".to_string()))
-        .map(|token| token.replace("Bearer ", ""))
-}
-
-pub async fn parse_body(req: &mut Request) -> Result<SearchRequest> {
-    req.json()
-        .await
-        .map_err(|e| worker::Error::RustError(format!("Failed to parse request body: {}", e)))
-}
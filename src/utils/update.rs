@@ -0,0 +1,353 @@
+use crate::models::error_models::{ApiResult, GistDbError};
+use crate::utils::filter::{compare, get_path, remove_path, set_path, values_equal};
+use serde_json::{Number, Value};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateOperator {
+    Set,
+    Unset,
+    Inc,
+    Mul,
+    Push,
+    Pull,
+    AddToSet,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone)]
+pub struct Update {
+    operations: Vec<(UpdateOperator, String, Value)>,
+}
+
+impl UpdateOperator {
+    fn name(self) -> &'static str {
+        match self {
+            UpdateOperator::Set => "$set",
+            UpdateOperator::Unset => "$unset",
+            UpdateOperator::Inc => "$inc",
+            UpdateOperator::Mul => "$mul",
+            UpdateOperator::Push => "$push",
+            UpdateOperator::Pull => "$pull",
+            UpdateOperator::AddToSet => "$addToSet",
+            UpdateOperator::Min => "$min",
+            UpdateOperator::Max => "$max",
+        }
+    }
+
+    fn parse(op: &str) -> ApiResult<Self> {
+        Ok(match op {
+            "$set" => UpdateOperator::Set,
+            "$unset" => UpdateOperator::Unset,
+            "$inc" => UpdateOperator::Inc,
+            "$mul" => UpdateOperator::Mul,
+            "$push" => UpdateOperator::Push,
+            "$pull" => UpdateOperator::Pull,
+            "$addToSet" => UpdateOperator::AddToSet,
+            "$min" => UpdateOperator::Min,
+            "$max" => UpdateOperator::Max,
+            _ => {
+                return Err(GistDbError::BadRequest(format!(
+                    "Unknown update operator '{}'",
+                    op
+                )))
+            }
+        })
+    }
+}
+
+fn arithmetic(op: UpdateOperator, current: &Number, operand: &Number) -> Option<Number> {
+    if let (Some(a), Some(b)) = (current.as_i64(), operand.as_i64()) {
+        let result = match op {
+            UpdateOperator::Inc => a.checked_add(b),
+            _ => a.checked_mul(b),
+        };
+        if let Some(result) = result {
+            return Some(result.into());
+        }
+    }
+    let (a, b) = (current.as_f64()?, operand.as_f64()?);
+    Number::from_f64(match op {
+        UpdateOperator::Inc => a + b,
+        _ => a * b,
+    })
+}
+
+fn each(operand: &Value) -> Vec<Value> {
+    match operand.get("$each") {
+        Some(Value::Array(items)) => items.clone(),
+        _ => vec![operand.clone()],
+    }
+}
+
+fn pull_matches(operand: &Value, item: &Value) -> bool {
+    match operand.get("$in") {
+        Some(Value::Array(options)) => options.iter().any(|o| values_equal(o, item)),
+        _ => values_equal(operand, item),
+    }
+}
+
+impl Update {
+    pub fn parse(spec: &Value) -> ApiResult<Self> {
+        let map = spec
+            .as_object()
+            .filter(|map| !map.is_empty())
+            .ok_or_else(|| {
+                GistDbError::BadRequest("Update must be a non-empty JSON object".to_string())
+            })?;
+
+        let mut operations = Vec::new();
+        let mut paths = HashSet::new();
+        for (op, fields) in map {
+            let operator = UpdateOperator::parse(op)?;
+            let fields = fields.as_object().ok_or_else(|| {
+                GistDbError::BadRequest(format!("'{}' expects an object of field paths", op))
+            })?;
+            for (path, operand) in fields {
+                if path.is_empty() || path.split('.').any(str::is_empty) {
+                    return Err(GistDbError::BadRequest(format!(
+                        "Invalid field path '{}'",
+                        path
+                    )));
+                }
+                if !paths.insert(path.clone()) {
+                    return Err(GistDbError::BadRequest(format!(
+                        "Field '{}' appears in more than one update operator",
+                        path
+                    )));
+                }
+                if matches!(operator, UpdateOperator::Inc | UpdateOperator::Mul)
+                    && !operand.is_number()
+                {
+                    return Err(GistDbError::BadRequest(format!(
+                        "'{}' on '{}' expects a number",
+                        op, path
+                    )));
+                }
+                operations.push((operator, path.clone(), operand.clone()));
+            }
+        }
+
+        Ok(Update { operations })
+    }
+
+    pub fn apply(&self, object: &mut Value) -> ApiResult<()> {
+        if !object.is_object() {
+            return Err(GistDbError::BadRequest(
+                "Update operators can only be applied to JSON objects".to_string(),
+            ));
+        }
+
+        for (operator, path, operand) in &self.operations {
+            let current = get_path(object, path).filter(|v| !v.is_null()).cloned();
+            let invalid = |expected: &str| {
+                GistDbError::BadRequest(format!(
+                    "Cannot apply {} to '{}': field is not {}",
+                    operator.name(),
+                    path,
+                    expected
+                ))
+            };
+
+            let updated = match operator {
+                UpdateOperator::Set => operand.clone(),
+                UpdateOperator::Unset => {
+                    remove_path(object, path);
+                    continue;
+                }
+                UpdateOperator::Inc | UpdateOperator::Mul => {
+                    let operand = operand.as_number().ok_or_else(|| invalid("a number"))?;
+                    let result = match (&current, operator) {
+                        (None, UpdateOperator::Inc) => operand.clone(),
+                        (None, _) => Number::from(0),
+                        (Some(Value::Number(n)), _) => arithmetic(*operator, n, operand)
+                            .ok_or_else(|| invalid("a finite number"))?,
+                        (Some(_), _) => return Err(invalid("a number")),
+                    };
+                    Value::Number(result)
+                }
+                UpdateOperator::Push | UpdateOperator::AddToSet | UpdateOperator::Pull => {
+                    let mut items = match current {
+                        None if *operator == UpdateOperator::Pull => continue,
+                        None => Vec::new(),
+                        Some(Value::Array(items)) => items,
+                        Some(_) => return Err(invalid("an array")),
+                    };
+                    match operator {
+                        UpdateOperator::Push => items.extend(each(operand)),
+                        UpdateOperator::AddToSet => {
+                            for item in each(operand) {
+                                if !items.iter().any(|i| values_equal(i, &item)) {
+                                    items.push(item);
+                                }
+                            }
+                        }
+                        _ => items.retain(|item| !pull_matches(operand, item)),
+                    }
+                    Value::Array(items)
+                }
+                UpdateOperator::Min | UpdateOperator::Max => {
+                    let wanted = match operator {
+                        UpdateOperator::Min => Ordering::Less,
+                        _ => Ordering::Greater,
+                    };
+                    match &current {
+                        None => operand.clone(),
+                        Some(current) => match compare(operand, current) {
+                            Some(ord) if ord == wanted => operand.clone(),
+                            Some(_) => continue,
+                            None => return Err(invalid("comparable with the operand")),
+                        },
+                    }
+                }
+            };
+            if !set_path(object, path, updated) {
+                return Err(GistDbError::BadRequest(format!(
+                    "Cannot apply {} to '{}': the path indexes past the end of an array or \
+                     uses a non-numeric key on one",
+                    operator.name(),
+                    path
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn apply(spec: Value, mut object: Value) -> ApiResult<Value> {
+        Update::parse(&spec)?.apply(&mut object)?;
+        Ok(object)
+    }
+
+    #[test]
+    fn inc_overflow_falls_back_to_float() {
+        let object = apply(json!({ "$inc": { "n": 1 } }), json!({ "n": i64::MAX })).unwrap();
+        assert!(object["n"].is_f64());
+        assert_eq!(object["n"].as_f64(), Some(i64::MAX as f64 + 1.0));
+
+        let object = apply(json!({ "$inc": { "n": -1 } }), json!({ "n": 5 })).unwrap();
+        assert_eq!(object, json!({ "n": 4 }));
+    }
+
+    #[test]
+    fn inc_and_mul_on_missing_fields() {
+        let object = apply(
+            json!({ "$inc": { "a.b": 2 }, "$mul": { "c": 3 } }),
+            json!({}),
+        )
+        .unwrap();
+        assert_eq!(object, json!({ "a": { "b": 2 }, "c": 0 }));
+    }
+
+    #[test]
+    fn inc_rejects_non_numeric_fields() {
+        assert!(apply(json!({ "$inc": { "n": 1 } }), json!({ "n": "1" })).is_err());
+        assert!(Update::parse(&json!({ "$inc": { "n": "1" } })).is_err());
+    }
+
+    #[test]
+    fn min_and_max_on_mismatched_types_fail() {
+        assert!(apply(json!({ "$min": { "a": 1 } }), json!({ "a": "2" })).is_err());
+        assert!(apply(json!({ "$max": { "a": "z" } }), json!({ "a": 2 })).is_err());
+        assert!(apply(json!({ "$max": { "a": true } }), json!({ "a": 0 })).is_err());
+        assert!(apply(json!({ "$min": { "a": [1] } }), json!({ "a": [2] })).is_err());
+    }
+
+    #[test]
+    fn min_and_max_keep_the_extreme_value() {
+        let object = apply(
+            json!({ "$min": { "low": 1 }, "$max": { "high": 1, "new": 5 } }),
+            json!({ "low": 3, "high": 3 }),
+        )
+        .unwrap();
+        assert_eq!(object, json!({ "low": 1, "high": 3, "new": 5 }));
+
+        let object = apply(
+            json!({ "$min": { "s": "apple" } }),
+            json!({ "s": "banana" }),
+        )
+        .unwrap();
+        assert_eq!(object, json!({ "s": "apple" }));
+    }
+
+    #[test]
+    fn pull_with_in_removes_every_listed_value() {
+        let object = apply(
+            json!({ "$pull": { "tags": { "$in": ["a", "c"] } } }),
+            json!({ "tags": ["a", "b", "c", "a"] }),
+        )
+        .unwrap();
+        assert_eq!(object, json!({ "tags": ["b"] }));
+
+        let object = apply(
+            json!({ "$pull": { "tags": "b" } }),
+            json!({ "tags": ["a", "b"] }),
+        )
+        .unwrap();
+        assert_eq!(object, json!({ "tags": ["a"] }));
+    }
+
+    #[test]
+    fn pull_on_missing_field_is_a_no_op() {
+        let object = apply(json!({ "$pull": { "tags": { "$in": [1] } } }), json!({})).unwrap();
+        assert_eq!(object, json!({}));
+        assert!(apply(json!({ "$pull": { "tags": 1 } }), json!({ "tags": 1 })).is_err());
+    }
+
+    #[test]
+    fn rejects_conflicting_paths() {
+        assert!(Update::parse(&json!({ "$set": { "a": 1 }, "$inc": { "a": 1 } })).is_err());
+        assert!(Update::parse(&json!({ "$set": { "a..b": 1 } })).is_err());
+        assert!(Update::parse(&json!({})).is_err());
+    }
+
+    #[test]
+    fn operators_reach_into_array_elements() {
+        let object = apply(
+            json!({
+                "$inc": { "items.0.qty": 1 },
+                "$set": { "items.1.name": "b" },
+                "$push": { "items.1.tags": "new" },
+                "$unset": { "items.0.note": "" },
+            }),
+            json!({ "items": [{ "qty": 1, "note": "x" }, { "qty": 5, "tags": [] }] }),
+        )
+        .unwrap();
+        assert_eq!(
+            object,
+            json!({ "items": [{ "qty": 2 }, { "qty": 5, "tags": ["new"], "name": "b" }] })
+        );
+    }
+
+    #[test]
+    fn paths_past_an_array_are_rejected() {
+        let object = json!({ "items": [{ "qty": 1 }] });
+        assert!(apply(json!({ "$inc": { "items.3.qty": 1 } }), object.clone()).is_err());
+        assert!(apply(json!({ "$set": { "items.qty": 1 } }), object.clone()).is_err());
+        assert!(apply(json!({ "$push": { "items.1.tags": "x" } }), object).is_err());
+    }
+
+    #[test]
+    fn array_operators_compare_numbers_by_value() {
+        let object = apply(
+            json!({ "$pull": { "a": { "$in": [1.0] } }, "$addToSet": { "b": { "$each": [2.0, 3] } } }),
+            json!({ "a": [1, 2], "b": [2] }),
+        )
+        .unwrap();
+        assert_eq!(object, json!({ "a": [2], "b": [2, 3] }));
+
+        let object = apply(
+            json!({ "$max": { "flag": true } }),
+            json!({ "flag": false }),
+        )
+        .unwrap();
+        assert_eq!(object, json!({ "flag": true }));
+    }
+}